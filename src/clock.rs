@@ -0,0 +1,38 @@
+use std::sync::LazyLock;
+use std::time::Instant;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// wall clock sampled once at startup, later readings advance it with a
+// monotonic clock so server timestamps never jump backwards
+static ANCHOR: LazyLock<(Instant, DateTime<Utc>)> = LazyLock::new(|| (Instant::now(), Utc::now()));
+
+pub fn now() -> DateTime<Utc> {
+    let (instant, wall) = *ANCHOR;
+    wall + instant.elapsed()
+}
+
+// milliseconds since the unix epoch, with sub-millisecond precision
+pub fn now_ms() -> f64 {
+    now().timestamp_micros() as f64 / 1000.0
+}
+
+// NTP-style exchange: the client sends t0, the server answers with the
+// receive (t1) and send (t2) times, all in milliseconds
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Ping {
+    pub t0: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Pong {
+    pub t0: f64,
+    pub t1: f64,
+    pub t2: f64,
+}
+
+impl Ping {
+    pub fn pong(self, t1: f64) -> Pong {
+        Pong { t0: self.t0, t1, t2: now_ms() }
+    }
+}
@@ -0,0 +1,180 @@
+use std::io::SeekFrom;
+use std::path::Path;
+use axum::body::{Body, Bytes};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::http::header::{
+    ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE,
+    ETAG, IF_NONE_MATCH, IF_RANGE, RANGE,
+};
+use axum::response::{IntoResponse, Response as AxumResponse};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use super::Error;
+use crate::unwrap;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct FileMeta<'a> {
+    pub path:   &'a Path,
+    pub mime:   &'a str,
+    pub size:   u64,
+    pub etag:   &'a str,
+}
+
+// single `bytes=` range, `None` if absent or unsupported (served in full),
+// `Some(Err(()))` if it cannot be satisfied
+fn parse_range(value: &str, size: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') { return None }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(size.saturating_sub(1))),
+        (Ok(start), Err(_)) if end.is_empty() => (start, size.saturating_sub(1)),
+        (Err(_), Ok(suffix)) if start.is_empty() && suffix > 0 => (size.saturating_sub(suffix), size.saturating_sub(1)),
+        _ => return None,
+    };
+    Some((range.0 < size).then_some(range).ok_or(()))
+}
+
+fn header_is(headers: &HeaderMap, name: impl axum::http::header::AsHeaderName, etag: &str) -> bool {
+    headers.get(name)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"))
+}
+
+// serve a file honoring `Range`, `If-Range` and `If-None-Match`
+pub async fn serve(headers: &HeaderMap, file: FileMeta<'_>) -> AxumResponse {
+    let etag = unwrap!(HeaderValue::from_str(file.etag).map_err(|_| Error::InvalidArgument("etag".to_string())));
+    if header_is(headers, IF_NONE_MATCH, file.etag) {
+        return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+    }
+
+    let if_range_ok = !headers.contains_key(IF_RANGE) || header_is(headers, IF_RANGE, file.etag);
+    let range = headers.get(RANGE)
+        .and_then(|v| v.to_str().ok())
+        .filter(|_| if_range_ok)
+        .and_then(|v| parse_range(v, file.size));
+
+    let (status, start, end) = match range {
+        None => (StatusCode::OK, 0, file.size.saturating_sub(1)),
+        Some(Ok((start, end))) => (StatusCode::PARTIAL_CONTENT, start, end),
+        Some(Err(())) => return (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(CONTENT_RANGE, format!("bytes */{}", file.size))],
+        ).into_response(),
+    };
+    let len = if file.size == 0 { 0 } else { end - start + 1 };
+
+    let mut reader = unwrap!(tokio::fs::File::open(file.path).await);
+    unwrap!(reader.seek(SeekFrom::Start(start)).await);
+    let stream = futures::stream::unfold(reader.take(len), |mut reader| async move {
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(Bytes::from(buf)), reader))
+            },
+            Err(e) => Some((Err(e), reader)),
+        }
+    });
+
+    let mut res = (status, Body::from_stream(stream)).into_response();
+    let res_headers = res.headers_mut();
+    res_headers.insert(ETAG, etag);
+    res_headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    res_headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+    if let Ok(mime) = HeaderValue::from_str(file.mime) {
+        res_headers.insert(CONTENT_TYPE, mime);
+    }
+    if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = format!("bytes {start}-{end}/{}", file.size);
+        res_headers.insert(CONTENT_RANGE, HeaderValue::from_str(&content_range).unwrap());
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = b"0123456789";
+    const ETAG: &str = "\"v1\"";
+
+    #[test]
+    fn ranges_are_clamped_to_the_file() {
+        assert_eq!(parse_range("bytes=2-5", 10), Some(Ok((2, 5))));
+        assert_eq!(parse_range("bytes=2-50", 10), Some(Ok((2, 9))));
+        assert_eq!(parse_range("bytes=7-", 10), Some(Ok((7, 9))));
+        assert_eq!(parse_range("bytes=-3", 10), Some(Ok((7, 9))));
+        assert_eq!(parse_range("bytes=-30", 10), Some(Ok((0, 9))));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges_are_told_apart_from_unsupported_ones() {
+        assert_eq!(parse_range("bytes=10-", 10), Some(Err(())));
+        assert_eq!(parse_range("bytes=10-20", 10), Some(Err(())));
+        assert_eq!(parse_range("bytes=-1", 0), Some(Err(())));
+        // served in full
+        assert_eq!(parse_range("bytes=0-1,4-5", 10), None);
+        assert_eq!(parse_range("bytes=5-2", 10), None);
+        assert_eq!(parse_range("bytes=-0", 10), None);
+        assert_eq!(parse_range("items=0-1", 10), None);
+    }
+
+    async fn get(headers: &[(axum::http::HeaderName, &str)]) -> (StatusCode, HeaderMap, Bytes) {
+        // tests run in parallel, every request gets its own file
+        static SEQ: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+        let seq = SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("strealome-{}-range-{seq}", std::process::id()));
+        tokio::fs::write(&path, DATA).await.unwrap();
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(name, HeaderValue::from_str(value).unwrap());
+        }
+        let res = serve(&map, FileMeta { path: &path, mime: "video/mp4", size: DATA.len() as u64, etag: ETAG }).await;
+        let (parts, body) = res.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let _ = tokio::fs::remove_file(&path).await;
+        (parts.status, parts.headers, body)
+    }
+
+    #[tokio::test]
+    async fn serves_the_requested_range() {
+        let (status, headers, body) = get(&[(RANGE, "bytes=2-5")]).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers[CONTENT_RANGE], "bytes 2-5/10");
+        assert_eq!(headers[CONTENT_LENGTH], "4");
+        assert_eq!(&body[..], b"2345");
+
+        let (status, _, body) = get(&[(RANGE, "bytes=-3"), (IF_RANGE, ETAG)]).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(&body[..], b"789");
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_whole_file() {
+        let (status, headers, body) = get(&[(RANGE, "bytes=0-1,4-5")]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!headers.contains_key(CONTENT_RANGE));
+        assert_eq!(&body[..], DATA);
+
+        // the file changed since the client cached the first part
+        let (status, _, body) = get(&[(RANGE, "bytes=2-5"), (IF_RANGE, "\"v0\"")]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(&body[..], DATA);
+    }
+
+    #[tokio::test]
+    async fn rejects_ranges_past_the_end() {
+        let (status, headers, body) = get(&[(RANGE, "bytes=10-")]).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(headers[CONTENT_RANGE], "bytes */10");
+        assert!(body.is_empty());
+
+        let (status, _, _) = get(&[(IF_NONE_MATCH, ETAG), (RANGE, "bytes=2-5")]).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+    }
+}
@@ -0,0 +1,29 @@
+use axum::{routing, Router};
+use axum::extract::Path;
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response as AxumResponse};
+
+use crate::controller::file::{self, FileMeta};
+use crate::service::media;
+use super::{Jwt, AppState, Response};
+
+async fn get(_jwt: Jwt, Path(id): Path<String>, headers: HeaderMap) -> AxumResponse {
+    let item = match media::get_media(&id) {
+        Ok(item) => item,
+        Err(e) => return Response::from(e).into_response(),
+    };
+    
+    let etag = item.etag();
+    file::serve(&headers, FileMeta {
+        path: &item.path,
+        mime: item.mime,
+        size: item.size,
+        etag: &etag,
+    }).await
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    let path = if path == "/" { "/{id}" } else { &format!("{path}/{{id}}") };
+    Router::new()
+        .route(path, routing::get(get))
+}
@@ -0,0 +1,19 @@
+use axum::Router;
+use serde::Serialize;
+
+use crate::service::media::{self, MediaItem};
+use super::{Jwt, AppState, Response};
+
+#[derive(Serialize)]
+struct GetResponse {
+    items: Vec<MediaItem>,
+}
+
+async fn get(_jwt: Jwt) -> Response {
+    Response::success(Some(GetResponse { items: media::list() }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::get(get))
+}
@@ -0,0 +1,17 @@
+mod list;
+mod file;
+
+use axum::Router;
+use super::{AppState, Jwt, Response};
+
+pub fn route(path: &str) -> Router<AppState> {
+    let inner = Router::new()
+        .merge(list::route("/list"))
+        .merge(file::route("/"));
+    
+    if path == "/" {
+        inner
+    } else {
+        Router::new().nest(path, inner)
+    }
+}
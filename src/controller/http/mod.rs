@@ -1,5 +1,8 @@
 mod room;
 mod chat;
+mod media;
+mod recording;
+mod stream;
 mod user;
 mod r#static;
 
@@ -12,6 +15,9 @@ pub fn route(path: &str, app_state: AppState) -> Router {
         .merge(chat::route("/chat"))
         .merge(user::route("/user"))
         .merge(room::route("/room"))
+        .merge(stream::route("/stream"))
+        .merge(media::route("/media"))
+        .merge(recording::route("/recording"))
         .with_state(app_state);
     
     if path == "/" {
@@ -0,0 +1,23 @@
+use axum::extract::State;
+use axum::{Json, Router};
+use serde::Deserialize;
+
+use crate::service::recording;
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    id: i32,
+}
+
+async fn post(jwt: Jwt, State(state): State<AppState>, Json(req): Json<PostRequest>) -> Response {
+    match recording::delete(state.repository, jwt.sub, req.id).await {
+        Ok(_) => Response::success::<()>(None),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::post(post))
+}
@@ -0,0 +1,51 @@
+use std::path::Path;
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, HeaderValue};
+use axum::http::header::CONTENT_DISPOSITION;
+use axum::response::{IntoResponse, Response as AxumResponse};
+use axum::Router;
+use serde::Deserialize;
+
+use crate::controller::Error;
+use crate::controller::file::{self, FileMeta};
+use crate::service::recording;
+use crate::unwrap;
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct GetRequest {
+    id: i32,
+}
+
+async fn get(
+    jwt: Jwt, State(state): State<AppState>,
+    Query(req): Query<GetRequest>, headers: HeaderMap
+) -> AxumResponse {
+    let recording = match recording::get_recording(state.repository, jwt.sub, req.id).await {
+        Ok(recording) => recording,
+        Err(e) => return Response::from(e).into_response(),
+    };
+    
+    // recordings in progress keep growing, so trust the file over the stored size
+    let path = Path::new(&recording.path);
+    let size = unwrap!(tokio::fs::metadata(path).await).len();
+    let etag = format!("\"{:x}-{:x}\"", recording.id, size);
+    let mut res = file::serve(&headers, FileMeta {
+        path,
+        mime: &recording.mime,
+        size,
+        etag: &etag,
+    }).await;
+    
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let disposition = format!("attachment; filename=\"{}-{file_name}\"", recording.room_link);
+    if let Ok(disposition) = HeaderValue::from_str(&disposition) {
+        res.headers_mut().insert(CONTENT_DISPOSITION, disposition);
+    }
+    res
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::get(get))
+}
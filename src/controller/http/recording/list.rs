@@ -0,0 +1,29 @@
+use axum::extract::{Query, State};
+use axum::Router;
+use serde::{Deserialize, Serialize};
+
+use crate::model::RecordingModel;
+use crate::service::recording;
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct GetRequest {
+    room: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GetResponse {
+    recordings: Vec<RecordingModel>,
+}
+
+async fn get(jwt: Jwt, State(state): State<AppState>, Query(req): Query<GetRequest>) -> Response {
+    let recordings = recording::list(state.repository, jwt.sub, req.room.as_deref()).await;
+    if let Err(e) = recordings { return e.into() }
+    
+    Response::success(Some(GetResponse { recordings: recordings.unwrap() }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::get(get))
+}
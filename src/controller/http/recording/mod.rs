@@ -0,0 +1,23 @@
+mod list;
+mod start;
+mod stop;
+mod download;
+mod delete;
+
+use axum::Router;
+use super::{AppState, Jwt, Response};
+
+pub fn route(path: &str) -> Router<AppState> {
+    let inner = Router::new()
+        .merge(list::route("/list"))
+        .merge(start::route("/start"))
+        .merge(stop::route("/stop"))
+        .merge(download::route("/download"))
+        .merge(delete::route("/delete"));
+    
+    if path == "/" {
+        inner
+    } else {
+        Router::new().nest(path, inner)
+    }
+}
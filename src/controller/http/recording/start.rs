@@ -0,0 +1,29 @@
+use axum::extract::State;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::model::RecordingModel;
+use crate::service::recording;
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+}
+
+#[derive(Serialize)]
+struct PostResponse {
+    recording: RecordingModel,
+}
+
+async fn post(jwt: Jwt, State(state): State<AppState>, Json(req): Json<PostRequest>) -> Response {
+    let recording = recording::start(state.repository, jwt.sub, &req.room).await;
+    if let Err(e) = recording { return e.into() }
+    
+    Response::success(Some(PostResponse { recording: recording.unwrap() }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::post(post))
+}
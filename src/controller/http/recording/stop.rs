@@ -0,0 +1,22 @@
+use axum::{Json, Router};
+use serde::Deserialize;
+
+use crate::service::recording;
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+}
+
+async fn post(jwt: Jwt, Json(req): Json<PostRequest>) -> Response {
+    match recording::stop(jwt.sub, &req.room) {
+        Ok(_) => Response::success::<()>(None),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::post(post))
+}
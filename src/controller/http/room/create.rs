@@ -2,11 +2,24 @@ use axum::extract::State;
 use axum::{Json, Router};
 use serde::{Deserialize, Serialize};
 use super::{AppState, Response, Jwt, RoomResp};
+use crate::model::RoomAccess;
 use crate::service::{room, user};
 
 #[derive(Deserialize, Debug)]
 struct PostRequest {
     name: String,
+    #[serde(default)]
+    access: RoomAccess,
+    password: Option<String>,
+    #[serde(default)]
+    invited: Vec<i32>,
+    #[serde(default = "default_listed")]
+    listed: bool,
+    capacity: Option<u32>,
+}
+
+fn default_listed() -> bool {
+    true
 }
 
 #[derive(Serialize, Debug)]
@@ -22,8 +35,11 @@ async fn post(
     if let Err(e) = user { return e.into() }
     let user = user.unwrap();
     
-    let PostRequest { name: room_name } = req;
-    let room = RoomResp::from(room::create_host_by(user.id, user.name, room_name), user.id);
+    let PostRequest { name: room_name, access, password, invited, listed, capacity } = req;
+    let param = room::RoomAccessParam { access, password, invited, listed, capacity };
+    let room = room::create_host_by(user.id, user.name, room_name, param).await;
+    if let Err(e) = room { return e.into() }
+    let room = RoomResp::from(room.unwrap(), user.id);
     Response::success(Some(PostResponse { room }))
 }
 
@@ -0,0 +1,24 @@
+use axum::{Json, Router};
+use serde::Deserialize;
+
+use crate::service::room;
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+    reason: Option<String>,
+}
+
+// host only, members are disconnected with the reason
+async fn post(jwt: Jwt, Json(req): Json<PostRequest>) -> Response {
+    match room::delete(&req.room, jwt.sub, req.reason).await {
+        Ok(_) => Response::success::<()>(None),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::post(post))
+}
@@ -1,8 +1,8 @@
-
-use axum::{routing, Router, Json};
+use axum::{routing, Router};
 use axum::extract::Query;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use super::{Jwt, AppState, Response, RoomResp};
+use crate::model::RoomMember;
 use crate::service::room;
 
 #[derive(Deserialize, Debug)]
@@ -10,6 +10,13 @@ struct GetRequest {
     room: String
 }
 
+#[derive(Serialize, Debug)]
+struct GetResponse {
+    #[serde(flatten)]
+    room: RoomResp,
+    members: Vec<RoomMember>,
+}
+
 async fn get(jwt: Jwt, Query(req): Query<GetRequest>) -> Response {
     let room = room::get_room_by_link(req.room.as_str());
     if let Err(e) = room { return e.into() }
@@ -17,10 +24,11 @@ async fn get(jwt: Jwt, Query(req): Query<GetRequest>) -> Response {
     
     if let Err(e) = room.contains_user(jwt.sub) { return e.into() }
     
-    Response::success(Some(RoomResp::from(room, jwt.sub)))
+    let members = room.member_list();
+    Response::success(Some(GetResponse { room: RoomResp::from(room, jwt.sub), members }))
 }
 
 pub fn route(path: &str) -> Router<AppState> {
     Router::new()
         .route(path, routing::get(get))
-}
\ No newline at end of file
+}
@@ -0,0 +1,30 @@
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use super::{AppState, Response, Jwt, RoomResp};
+use crate::service::room;
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+    password: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct PostResponse {
+    room: RoomResp,
+}
+
+async fn post(jwt: Jwt, Json(req): Json<PostRequest>) -> Response {
+    let room = room::get_room_by_link(&req.room);
+    if let Err(e) = room { return e.into() }
+    let room = room.unwrap();
+
+    if let Err(e) = room.admit(jwt.sub, req.password.as_deref()) { return e.into() }
+    let room = RoomResp::from(room, jwt.sub);
+    Response::success(Some(PostResponse { room }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::post(post))
+}
@@ -0,0 +1,43 @@
+use axum::extract::Query;
+use axum::Router;
+use serde::{Deserialize, Serialize};
+
+use crate::service::room::{self, ListParam, RoomSort, RoomStatus};
+use super::{Jwt, AppState, Response, RoomResp};
+
+const DEFAULT_LIMIT: usize = 20;
+
+#[derive(Deserialize, Debug)]
+struct GetRequest {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    status: RoomStatus,
+    #[serde(default)]
+    sort: RoomSort,
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct GetResponse {
+    rooms: Vec<RoomResp>,
+    next_cursor: Option<String>,
+}
+
+// public directory of listed rooms
+async fn get(jwt: Jwt, Query(req): Query<GetRequest>) -> Response {
+    let GetRequest { q: query, status, sort, cursor, limit } = req;
+    let param = ListParam { query, status, sort, cursor, limit: limit.unwrap_or(DEFAULT_LIMIT) };
+    let res = room::list(&param);
+    if let Err(e) = res { return e.into() }
+    let (rooms, next_cursor) = res.unwrap();
+
+    let rooms = rooms.into_iter().map(|r| RoomResp::from(r, jwt.sub)).collect();
+    Response::success(Some(GetResponse { rooms, next_cursor }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::get(get))
+}
@@ -1,7 +1,22 @@
 mod my;
+mod list;
 mod create;
+mod join;
+mod kick;
+mod ban;
+mod unban;
+mod mute;
+mod unmute;
+mod role;
+mod update;
+mod delete;
+mod transfer;
 mod response;
 mod detail;
+mod stats;
+mod thumbnail;
+mod subtitle;
+mod subtitle_delete;
 
 use axum::Router;
 use super::{AppState, Response, Jwt};
@@ -11,8 +26,23 @@ use response::RoomResp;
 pub fn route(path: &str) -> Router<AppState> {
     let inner = Router::new()
         .merge(my::route("/my"))
+        .merge(list::route("/list"))
         .merge(detail::route("/detail"))
-        .merge(create::route("/create"));
+        .merge(stats::route("/stats"))
+        .merge(thumbnail::route("/thumbnail"))
+        .merge(subtitle::route("/subtitle"))
+        .merge(subtitle_delete::route("/subtitle/delete"))
+        .merge(create::route("/create"))
+        .merge(join::route("/join"))
+        .merge(kick::route("/kick"))
+        .merge(ban::route("/ban"))
+        .merge(unban::route("/unban"))
+        .merge(mute::route("/mute"))
+        .merge(unmute::route("/unmute"))
+        .merge(role::route("/role"))
+        .merge(update::route("/update"))
+        .merge(delete::route("/delete"))
+        .merge(transfer::route("/transfer"));
     
     if path == "/" {
         inner
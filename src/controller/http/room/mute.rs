@@ -0,0 +1,28 @@
+use axum::{Json, Router};
+use serde::Deserialize;
+
+use crate::service::room;
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+    user_id: i32,
+    reason: Option<String>,
+}
+
+// moderators and the host, the member keeps watching but cannot chat
+async fn post(jwt: Jwt, Json(req): Json<PostRequest>) -> Response {
+    let room = room::get_room_by_link(&req.room);
+    if let Err(e) = room { return e.into() }
+
+    match room.unwrap().mute(jwt.sub, req.user_id, req.reason).await {
+        Ok(_) => Response::success::<()>(None),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::post(post))
+}
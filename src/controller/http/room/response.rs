@@ -1,15 +1,26 @@
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use crate::model::{Permission, RoomAccess, RoomRole, StreamTrack, SubtitleTrack};
+use crate::service::media::MediaItem;
 use crate::service::room::Room;
 
 #[derive(Serialize, Debug)]
 pub struct RoomResp {
     name:       String,
     hosting:    bool,
+    role:       RoomRole, // of the requesting user
+    permissions: Vec<Permission>,
     host:       String,
     share_link: String,
     member_cnt: usize,
+    capacity:   usize,
+    access:     RoomAccess,
+    listed:     bool,
     created_at: DateTime<Utc>,
+    source:     Option<MediaItem>,
+    publishers: Vec<StreamTrack>,
+    thumbnail_url: Option<String>,
+    subtitles:  Vec<SubtitleTrack>,
 }
 
 impl RoomResp {
@@ -20,7 +31,16 @@ impl RoomResp {
             share_link: room.share_link(),
             created_at: room.created_at(),
             member_cnt: room.user_len(),
+            capacity:   room.capacity(),
+            access:     room.access(),
+            listed:     room.is_listed(),
             hosting:    room.host_id() == host_id,
+            role:       room.role(host_id),
+            permissions: room.permissions(host_id),
+            source:     room.source(),
+            publishers: room.tracks(),
+            thumbnail_url: room.thumbnail_url(),
+            subtitles:  room.subtitles(),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,29 @@
+use axum::{Json, Router};
+use serde::Deserialize;
+
+use crate::model::RoomRole;
+use crate::service::room;
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+    user_id: i32,
+    role: RoomRole,
+}
+
+// host only, assigns moderator, member or viewer
+async fn post(jwt: Jwt, Json(req): Json<PostRequest>) -> Response {
+    let room = room::get_room_by_link(&req.room);
+    if let Err(e) = room { return e.into() }
+
+    match room.unwrap().set_role(jwt.sub, req.user_id, req.role).await {
+        Ok(_) => Response::success::<()>(None),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::post(post))
+}
@@ -0,0 +1,26 @@
+use axum::{routing, Router};
+use axum::extract::Query;
+use serde::Deserialize;
+use super::{Jwt, AppState, Response};
+use crate::service::room;
+
+#[derive(Deserialize, Debug)]
+struct GetRequest {
+    room: String
+}
+
+// viewer health summary, publishers only
+async fn get(jwt: Jwt, Query(req): Query<GetRequest>) -> Response {
+    let room = room::get_room_by_link(req.room.as_str());
+    if let Err(e) = room { return e.into() }
+    
+    match room.unwrap().stream_health(jwt.sub) {
+        Ok(health) => Response::success(Some(health)),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, routing::get(get))
+}
@@ -0,0 +1,61 @@
+use axum::{routing, Router};
+use axum::body::Bytes;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use axum::response::{IntoResponse, Response as AxumResponse};
+use serde::{Deserialize, Serialize};
+use super::{Jwt, AppState, Response};
+use crate::model::SubtitleTrack;
+use crate::service::room;
+
+#[derive(Deserialize, Debug)]
+struct GetRequest {
+    room: String,
+    id: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+    label: String,
+    lang: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct PostResponse {
+    subtitle: SubtitleTrack,
+}
+
+// tracks never change once uploaded
+async fn get(jwt: Jwt, Query(req): Query<GetRequest>) -> AxumResponse {
+    let room = match room::get_room_by_link(&req.room) {
+        Ok(room) => room,
+        Err(e) => return Response::from(e).into_response(),
+    };
+    if let Err(e) = room.stream_role(jwt.sub) { return Response::from(e).into_response() }
+    let Some(track) = room.subtitle(req.id) else {
+        return Response::code(StatusCode::NOT_FOUND).into_response();
+    };
+    
+    (
+        [(CONTENT_TYPE, "text/vtt; charset=utf-8"), (CACHE_CONTROL, "private, max-age=86400, immutable")],
+        track.vtt.clone(),
+    ).into_response()
+}
+
+// raw SRT or WebVTT body for the media currently loaded, host only
+async fn post(jwt: Jwt, Query(req): Query<PostRequest>, body: Bytes) -> Response {
+    let room = room::get_room_by_link(&req.room);
+    if let Err(e) = room { return e.into() }
+    
+    match room.unwrap().add_subtitle(jwt.sub, req.label, req.lang, &body).await {
+        Ok(subtitle) => Response::success(Some(PostResponse { subtitle })),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, routing::get(get).post(post))
+}
@@ -0,0 +1,72 @@
+use axum::{routing, Router};
+use axum::body::Bytes;
+use axum::extract::Query;
+use axum::http::{HeaderMap, StatusCode};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use axum::response::{IntoResponse, Response as AxumResponse};
+use serde::Deserialize;
+use super::{Jwt, AppState, Response};
+use crate::service::room;
+use crate::service::thumbnail::Thumbnail;
+
+#[derive(Deserialize, Debug)]
+struct GetRequest {
+    room: String,
+    v: Option<i64>, // version from `thumbnail_url`
+}
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+}
+
+// versioned urls never change content and may be cached for good
+async fn get(jwt: Jwt, Query(req): Query<GetRequest>, headers: HeaderMap) -> AxumResponse {
+    let room = match room::get_room_by_link(&req.room) {
+        Ok(room) => room,
+        Err(e) => return Response::from(e).into_response(),
+    };
+    // listed rooms show their preview in the directory, before anyone joins
+    if !room.is_listed()
+        && let Err(e) = room.stream_role(jwt.sub) {
+        return Response::from(e).into_response();
+    }
+    let Some(thumbnail) = room.thumbnail() else {
+        return Response::code(StatusCode::NOT_FOUND).into_response();
+    };
+    
+    let etag = thumbnail.etag();
+    let cache_control = if req.v == Some(thumbnail.version()) {
+        "private, max-age=86400, immutable"
+    } else {
+        "private, no-cache"
+    };
+    let not_modified = headers.get(IF_NONE_MATCH).is_some_and(|v| v.as_bytes() == etag.as_bytes());
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, [(ETAG, etag), (CACHE_CONTROL, cache_control.to_string())]).into_response();
+    }
+    (
+        [(CONTENT_TYPE, thumbnail.mime.to_string()), (ETAG, etag), (CACHE_CONTROL, cache_control.to_string())],
+        thumbnail.data.clone(),
+    ).into_response()
+}
+
+// raw JPEG or PNG body, host only
+async fn post(jwt: Jwt, Query(req): Query<PostRequest>, headers: HeaderMap, body: Bytes) -> Response {
+    let room = room::get_room_by_link(&req.room);
+    if let Err(e) = room { return e.into() }
+    
+    let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let thumbnail = Thumbnail::new(content_type, body);
+    if let Err(e) = thumbnail { return e.into() }
+    
+    match room.unwrap().set_thumbnail(jwt.sub, thumbnail.unwrap()) {
+        Ok(_) => Response::success::<()>(None),
+        Err(e) => e.into(),
+    }
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, routing::get(get).post(post))
+}
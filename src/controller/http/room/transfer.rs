@@ -0,0 +1,42 @@
+use axum::extract::State;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::service::{room, user};
+use super::{Jwt, AppState, Response, RoomResp};
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+    user_id: Option<i32>,
+    name: Option<String>, // used when `user_id` is absent
+}
+
+#[derive(Serialize, Debug)]
+struct PostResponse {
+    room: RoomResp,
+}
+
+// host only, the new host must have joined the room
+async fn post(
+    jwt: Jwt, State(state): State<AppState>,
+    Json(req): Json<PostRequest>
+) -> Response {
+    let target = match (req.user_id, req.name.as_deref()) {
+        (Some(user_id), _) => user::get_user_by_id(state.repository, user_id).await,
+        (None, Some(name)) => user::get_user_by_name(state.repository, name).await,
+        (None, None) => return room::RoomError::InvalidArgument("user_id or name is required").into(),
+    };
+    if let Err(e) = target { return e.into() }
+    let target = target.unwrap();
+
+    let room = room::transfer(&req.room, jwt.sub, target.id, target.name).await;
+    if let Err(e) = room { return e.into() }
+    let room = RoomResp::from(room.unwrap(), jwt.sub);
+    Response::success(Some(PostResponse { room }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::post(post))
+}
@@ -0,0 +1,40 @@
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::model::RoomAccess;
+use crate::service::room::{self, RoomUpdateParam};
+use super::{Jwt, AppState, Response, RoomResp};
+
+#[derive(Deserialize, Debug)]
+struct PostRequest {
+    room: String,
+    name: Option<String>,
+    access: Option<RoomAccess>,
+    password: Option<String>,
+    invited: Option<Vec<i32>>,
+    listed: Option<bool>,
+    capacity: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+struct PostResponse {
+    room: RoomResp,
+}
+
+// host only, omitted fields are left as they are
+async fn post(jwt: Jwt, Json(req): Json<PostRequest>) -> Response {
+    let room = room::get_room_by_link(&req.room);
+    if let Err(e) = room { return e.into() }
+    let room = room.unwrap();
+
+    let PostRequest { name, access, password, invited, listed, capacity, .. } = req;
+    let param = RoomUpdateParam { name, access, password, invited, listed, capacity };
+    if let Err(e) = room.update(jwt.sub, param).await { return e.into() }
+    let room = RoomResp::from(room, jwt.sub);
+    Response::success(Some(PostResponse { room }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::post(post))
+}
@@ -0,0 +1,30 @@
+use axum::{Router, routing};
+use axum::extract::Path;
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use axum::response::{IntoResponse, Response as AxumResponse};
+
+use crate::controller::Response;
+use crate::service::hls::{self, HlsFile};
+use super::{AppState, Jwt};
+
+async fn get(jwt: Jwt, Path((room_link, file)): Path<(String, String)>) -> AxumResponse {
+    let payload = file.parse::<HlsFile>()
+        .and_then(|file| hls::get_file(&room_link, jwt.sub, file));
+    let payload = match payload {
+        Ok(payload) => payload,
+        Err(e) => return Response::from(e).into_response(),
+    };
+    
+    // gated by membership, shared caches must not keep it
+    let cache_control = if payload.immutable { "private, max-age=3600, immutable" } else { "private, no-cache" };
+    (
+        [(CONTENT_TYPE, payload.content_type), (CACHE_CONTROL, cache_control)],
+        payload.body,
+    ).into_response()
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    let path = if path == "/" { "/{room}/hls/{file}" } else { &format!("{path}/{{room}}/hls/{{file}}") };
+    Router::new()
+        .route(path, routing::get(get))
+}
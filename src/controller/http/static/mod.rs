@@ -1,6 +1,7 @@
 use axum::Router;
 use super::{ AppState, Jwt, Error };
 
+mod hls;
 mod index;
 mod login;
 mod register;
@@ -10,6 +11,7 @@ pub fn route(path: &str) -> Router<AppState> {
     let inner = Router::new()
         .merge(index::route("/"))
         .merge(stream::route("/share"))
+        .merge(hls::route("/share"))
         .merge(login::route("/login"))
         .merge(register::route("/register"));
     
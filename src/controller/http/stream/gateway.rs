@@ -0,0 +1,40 @@
+use axum::extract::{Query, State};
+use axum::Router;
+use serde::{Deserialize, Serialize};
+
+use crate::model::StreamRole;
+use crate::service::room;
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct GetRequest {
+    room: String,
+}
+
+#[derive(Serialize)]
+struct GetResponse {
+    gateway_token: String,
+    role: StreamRole,
+}
+
+async fn get(jwt: Jwt, State(state): State<AppState>, Query(req): Query<GetRequest>) -> Response {
+    let room = room::get_room_by_link(&req.room);
+    if let Err(e) = room { return e.into() }
+    let room = room.unwrap();
+    
+    let role = room.stream_role(jwt.sub);
+    if let Err(e) = role { return e.into() }
+    let role = role.unwrap();
+
+    let gateway_token = Jwt::stream_ws(jwt.sub, state.jwt_exp_duration, room.share_link(), role);
+    let gateway_token = match gateway_token.encode(&state.jwt_secret) {
+        Ok(token) => token,
+        Err(_) => return Response::error("Failed to encode gateway token"),
+    };
+    Response::success(Some(GetResponse { gateway_token, role }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::get(get))
+}
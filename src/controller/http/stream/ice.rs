@@ -0,0 +1,21 @@
+use axum::Router;
+use serde::Serialize;
+
+use crate::service::ice::{self, IceServer};
+use super::{Jwt, AppState, Response};
+
+#[derive(Serialize)]
+struct GetResponse {
+    ice_servers: Vec<IceServer>,
+    ttl: i64, // seconds the TURN credentials stay valid
+}
+
+async fn get(jwt: Jwt) -> Response {
+    let (ice_servers, ttl) = ice::servers(jwt.sub);
+    Response::success(Some(GetResponse { ice_servers, ttl }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::get(get))
+}
@@ -0,0 +1,23 @@
+mod gateway;
+mod grant;
+mod ice;
+mod revoke;
+mod stats;
+
+use axum::Router;
+use super::{AppState, Jwt, Response};
+
+pub fn route(path: &str) -> Router<AppState> {
+    let inner = Router::new()
+        .merge(gateway::route("/gateway"))
+        .merge(grant::route("/grant"))
+        .merge(ice::route("/ice"))
+        .merge(revoke::route("/revoke"))
+        .merge(stats::route("/stats"));
+    
+    if path == "/" {
+        inner
+    } else {
+        Router::new().nest(path, inner)
+    }
+}
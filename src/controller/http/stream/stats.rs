@@ -0,0 +1,33 @@
+use axum::extract::Query;
+use axum::Router;
+use serde::{Deserialize, Serialize};
+
+use crate::model::Permission;
+use crate::service::relay::{self, ViewerStats};
+use crate::service::room::{self, RoomError};
+use super::{Jwt, AppState, Response};
+
+#[derive(Deserialize, Debug)]
+struct GetRequest {
+    room: String,
+}
+
+#[derive(Serialize)]
+struct GetResponse {
+    viewers: Vec<ViewerStats>,
+}
+
+// per-viewer relay counters, publishers only
+async fn get(jwt: Jwt, Query(req): Query<GetRequest>) -> Response {
+    let room = room::get_room_by_link(&req.room);
+    if let Err(e) = room { return e.into() }
+    let room = room.unwrap();
+    
+    if !room.can_publish(jwt.sub) { return RoomError::Forbidden(Permission::Publish).into() }
+    Response::success(Some(GetResponse { viewers: relay::viewer_stats(&room.share_link()) }))
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(path, axum::routing::get(get))
+}
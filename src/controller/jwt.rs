@@ -14,6 +14,7 @@ use jsonwebtoken::{encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize, Serializer, Deserializer};
 
 use super::AppState;
+use crate::model::StreamRole;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JwtDomain {
@@ -46,6 +47,12 @@ pub struct Jwt {
     pub exp: i64,
     pub iat: i64,
     pub dom: JwtDomain,
+    
+    // only carried by stream tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<StreamRole>,
 }
 
 impl Jwt {
@@ -57,6 +64,8 @@ impl Jwt {
             exp,
             iat,
             dom: domain,
+            room: None,
+            role: None,
         }
     }
     
@@ -68,14 +77,23 @@ impl Jwt {
         Self::new(sub, exp_duration_s, JwtDomain::WebSocketChat)
     }
     
-    pub fn stream_ws(sub: i32, exp_duration_s: Duration) -> Self {
-        Self::new(sub, exp_duration_s, JwtDomain::WebSocketStream)
+    pub fn stream_ws(sub: i32, exp_duration_s: Duration, room: String, role: StreamRole) -> Self {
+        Self {
+            room: Some(room),
+            role: Some(role),
+            ..Self::new(sub, exp_duration_s, JwtDomain::WebSocketStream)
+        }
     }
     
     pub fn verify(&self, domain: JwtDomain) -> bool {
         domain == self.dom && chrono::Local::now().timestamp() < self.exp
     }
     
+    // role granted for the given room, if the token was issued for it
+    pub fn stream_role(&self, room_link: &str) -> Option<StreamRole> {
+        (self.room.as_deref() == Some(room_link)).then_some(self.role).flatten()
+    }
+    
     pub fn encode(&self, secret: &str) -> Result<String, Error> {
         let header = Header::new(jsonwebtoken::Algorithm::HS256);
         let key = EncodingKey::from_secret(secret.as_bytes());
@@ -1,4 +1,5 @@
 mod error;
+mod file;
 mod response;
 mod jwt;
 mod http;
@@ -56,15 +57,24 @@ pub async fn listen<A: ToSocketAddrs>(
     let chat_ws_state = AppState {
         repository: repo.clone(),
         jwt_auth_method: JwtAuthMethod::Headers,
-        jwt_secret,
+        jwt_secret: jwt_secret.clone(),
         jwt_exp_duration,
         jwt_exp_dur_long,
         jwt_domain: JwtDomain::WebSocketChat,
     };
     
+    let stream_ws_state = AppState {
+        repository: repo.clone(),
+        jwt_auth_method: JwtAuthMethod::Headers,
+        jwt_secret,
+        jwt_exp_duration,
+        jwt_exp_dur_long,
+        jwt_domain: JwtDomain::WebSocketStream,
+    };
+    
     let app = Router::new()
         .merge(http::route("/", http_state))
-        .merge(ws::route("/ws", chat_ws_state));
+        .merge(ws::route("/ws", chat_ws_state, stream_ws_state));
 
     let listener = TcpListener::bind(addr).await.unwrap();
     println!("Listening on http://{}", listener.local_addr().unwrap());
@@ -9,7 +9,15 @@ async fn upgrade(
     jwt: Jwt, State(state): State<AppState>,
     ws: WebSocketUpgrade, Path(room_link): Path<String>
 ) -> AxumResponse {
-    if let Err(e) = room::get_room_by_link(&room_link) {
+    let room = match room::get_room_by_link(&room_link) {
+        Ok(room) => room,
+        Err(e) => return Response::from(e).into_response(),
+    };
+    // access is checked by `/room/join`, the socket only admits members
+    if !room.is_member(jwt.sub) {
+        return Response::from(room::RoomError::NotJoined).into_response();
+    }
+    if let Err(e) = room.check_capacity(jwt.sub) {
         return Response::from(e).into_response();
     }
 
@@ -0,0 +1,36 @@
+use axum::{routing, Router, response::Response as AxumResponse};
+use axum::extract::{Path, Query, WebSocketUpgrade};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+use super::{Response, AppState, Jwt};
+use crate::service::relay;
+
+#[derive(Deserialize, Debug)]
+struct UpgradeQuery {
+    track: Option<i32>, // publisher to watch, the host by default
+}
+
+async fn upgrade(
+    jwt: Jwt, ws: WebSocketUpgrade,
+    Path(room_link): Path<String>, Query(query): Query<UpgradeQuery>
+) -> AxumResponse {
+    let Some(role) = jwt.stream_role(&room_link) else {
+        return Response::code(StatusCode::FORBIDDEN).into_response();
+    };
+
+    ws.on_upgrade(
+        async move |socket| {
+            if let Err(e) = relay::handle_websocket(socket, room_link, jwt.sub, role, query.track).await {
+                eprintln!("Error: {}", e)
+            }
+        }
+    )
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    let path = if path == "/" { "/{room}" } else { &format!("{path}/{{room}}") };
+    Router::new()
+        .route(path, routing::get(upgrade))
+}
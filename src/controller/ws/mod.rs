@@ -1,12 +1,15 @@
 mod chat;
+mod media;
+mod stream;
 
 use axum::Router;
 use super::{AppState, Jwt, Response, Error};
 
-pub fn route(path: &str, app_state: AppState) -> Router {
+pub fn route(path: &str, chat_state: AppState, stream_state: AppState) -> Router {
     let inner = Router::new()
-        .merge(chat::route("/chat"))
-        .with_state(app_state);
+        .merge(chat::route("/chat").with_state(chat_state))
+        .merge(stream::route("/stream").with_state(stream_state.clone()))
+        .merge(media::route("/media").with_state(stream_state));
 
     if path == "/" {
         inner
@@ -0,0 +1,27 @@
+use axum::{routing, Router, response::Response as AxumResponse};
+use axum::extract::{Path, WebSocketUpgrade};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+
+use super::{Response, AppState, Jwt};
+use crate::signal;
+
+async fn upgrade(jwt: Jwt, ws: WebSocketUpgrade, Path(room_link): Path<String>) -> AxumResponse {
+    let Some(role) = jwt.stream_role(&room_link) else {
+        return Response::code(StatusCode::FORBIDDEN).into_response();
+    };
+
+    ws.on_upgrade(
+        async move |socket| {
+            if let Err(e) = signal::handle_websocket(socket, room_link, jwt.sub, role).await {
+                eprintln!("Error: {}", e)
+            }
+        }
+    )
+}
+
+pub fn route(path: &str) -> Router<AppState> {
+    let path = if path == "/" { "/{room}" } else { &format!("{path}/{{room}}") };
+    Router::new()
+        .route(path, routing::get(upgrade))
+}
@@ -1,3 +1,4 @@
+mod clock;
 mod controller;
 mod model;
 mod repository;
@@ -9,6 +10,10 @@ use chrono::Duration;
 use tokio::task::JoinHandle;
 use repository::{ Repo, RepoConfig };
 use crate::repository::Repository;
+use crate::service::media::{self, MediaConfig};
+use crate::service::room::{self, RoomConfig};
+use crate::service::recording::{self, RecordingConfig};
+use crate::service::ice::IceConfig;
 
 
 static REPO_CFG: RepoConfig = RepoConfig {
@@ -19,6 +24,32 @@ static REPO_CFG: RepoConfig = RepoConfig {
     database: None,
 };
 
+static MEDIA_CFG: MediaConfig = MediaConfig {
+    root: "res/media",
+};
+
+static RECORDING_CFG: RecordingConfig = RecordingConfig {
+    root: "res/recordings",
+};
+
+static ICE_CFG: IceConfig = IceConfig {
+    stun_urls: &["stun:stun.l.google.com:19302"],
+    turn_urls: &[],
+    turn_secret: "secret",
+    credential_ttl: 60 * 60 * 12,
+};
+
+static ROOM_CFG: RoomConfig = RoomConfig {
+    idle_timeout: 60 * 60 * 6,
+    reap_interval: 60,
+    tombstone_ttl: 60 * 60 * 24 * 7,
+    max_hosted_rooms: 5,
+    max_members: 200,
+    admins: &[],
+    heartbeat_interval: 20,
+    heartbeat_timeout: 60,
+};
+
 async fn ctrl_c_task() -> JoinHandle<()> {
     tokio::spawn(async move {
         tokio::signal::ctrl_c().await.unwrap();
@@ -28,10 +59,23 @@ async fn ctrl_c_task() -> JoinHandle<()> {
 #[tokio::main]
 async fn main() {
     let repo = Repo::conn().await;
+    let media_cnt = media::scan(&MEDIA_CFG).await;
+    println!("Indexed {media_cnt} media file(s) under {}", MEDIA_CFG.root);
+    let repo: Arc<dyn Repository> = Arc::new(repo);
+    match room::load(repo.clone()).await {
+        Ok(cnt) => println!("Restored {cnt} room(s)"),
+        Err(e) => eprintln!("Failed to restore rooms: {e}"),
+    }
+    match recording::recover(repo.clone()).await {
+        Ok(0) => {},
+        Ok(cnt) => println!("Finalized {cnt} interrupted recording(s)"),
+        Err(e) => eprintln!("Failed to finalize interrupted recordings: {e}"),
+    }
+    let reaper_task = room::spawn_reaper(&ROOM_CFG);
 
     let mut serve_task = controller::listen(
         "0.0.0.0:80",
-        Arc::new(repo),
+        repo,
         "secret".to_string(),
         Duration::minutes(30),
         Duration::days(3),
@@ -42,14 +86,16 @@ async fn main() {
     tokio::select! {
         _ = &mut ctrl_c_task => {
             println!("Ctrl-C received, shutting down.");
-            serve_task.abort()
+            serve_task.abort();
+            reaper_task.abort()
         },
         res = &mut serve_task => {
             println!("Server stopped.");
             if let Err(e) = res.unwrap() {
                 println!("Server error: {}", e);
             }
-            ctrl_c_task.abort()
+            ctrl_c_task.abort();
+            reaper_task.abort()
         }
     }
 }
@@ -5,6 +5,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::sync::RwLock;
 
+use crate::clock;
+
 static ID: AtomicI32  = AtomicI32::new(0);
 pub fn gen_id() -> i32 {
     ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
@@ -24,7 +26,7 @@ pub struct ChatMessage {
 impl ChatMessage {
     pub fn new(author_id: i32, room: String, content: ChatMessageContent) -> Self {
         let id = gen_id();
-        let created_at = Utc::now();
+        let created_at = clock::now();
         let formatted = json!({
             "id": id,
             "author_id": author_id,
@@ -1,5 +1,17 @@
 mod user;
 mod chat;
+mod room;
+mod room_model;
+mod role;
+mod recording;
+mod stream;
+mod subtitle;
 
 pub use user::UserModel;
-pub use chat::{ChatMessage, ChatMessageContent};
\ No newline at end of file
+pub use chat::{ChatMessage, ChatMessageContent};
+pub use room::{RoomMessage, RoomEvent, RoomCommand, RoomMember, CloseCode, ModerationAction, PlaybackCommand, PlaybackState};
+pub use room_model::{RoomAccess, RoomModel};
+pub use role::{RoomRole, Permission};
+pub use recording::RecordingModel;
+pub use stream::{StreamRole, StreamTrack, StatsReport, StreamHealth};
+pub use subtitle::SubtitleTrack;
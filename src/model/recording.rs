@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingModel {
+    pub id: i32,
+    pub room_link: String,
+    pub host_id: i32,
+    pub mime: String,
+    #[serde(skip)]
+    pub path: String,
+    pub size: i64,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+impl RecordingModel {
+    pub fn new_recording(room_link: String, host_id: i32, mime: String, path: String) -> Self {
+        Self {
+            id: 0,
+            room_link,
+            host_id,
+            mime,
+            path,
+            size: 0,
+            started_at: Utc::now(),
+            ended_at: None,
+        }
+    }
+}
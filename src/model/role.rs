@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomRole {
+    Host,
+    Moderator,
+    #[default]
+    Member,
+    Viewer, // watch only
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Chat,
+    PostFile,
+    ControlPlayback,
+    Publish,
+    Moderate,
+    EditSettings,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 6] = [
+        Permission::Chat, Permission::PostFile, Permission::ControlPlayback,
+        Permission::Publish, Permission::Moderate, Permission::EditSettings,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::Chat => "chat",
+            Permission::PostFile => "post_file",
+            Permission::ControlPlayback => "control_playback",
+            Permission::Publish => "publish",
+            Permission::Moderate => "moderate",
+            Permission::EditSettings => "edit_settings",
+        }
+    }
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl RoomRole {
+    // the permission matrix, per-user grants such as co-hosting are layered on top by `Room`
+    pub fn allows(&self, permission: Permission) -> bool {
+        use Permission::*;
+        match self {
+            RoomRole::Host => true,
+            RoomRole::Moderator => matches!(permission, Chat | PostFile | ControlPlayback | Moderate),
+            RoomRole::Member => matches!(permission, Chat | PostFile),
+            RoomRole::Viewer => false,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoomRole::Host => "host",
+            RoomRole::Moderator => "moderator",
+            RoomRole::Member => "member",
+            RoomRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "host" => Some(RoomRole::Host),
+            "moderator" => Some(RoomRole::Moderator),
+            "member" => Some(RoomRole::Member),
+            "viewer" => Some(RoomRole::Viewer),
+            _ => None,
+        }
+    }
+}
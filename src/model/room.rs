@@ -0,0 +1,349 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+
+use crate::clock::{self, Ping, Pong};
+use super::chat::{gen_id, ChatMessage};
+use super::stream::{StatsReport, StreamHealth, StreamTrack};
+use super::subtitle::SubtitleTrack;
+use super::role::RoomRole;
+use super::room_model::RoomAccess;
+
+// everything pushed to a room member's socket
+#[derive(Debug)]
+pub enum RoomMessage {
+    Chat(ChatMessage),
+    Event(String), // pre-serialized, see `RoomMessage::event`
+}
+
+// websocket close codes, in the range reserved for applications
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseCode {
+    RoomClosed = 4000,
+    Kicked = 4001,
+    Banned = 4002,
+    RoomFull = 4003,
+    Timeout = 4004, // no frame within the heartbeat timeout
+}
+
+impl RoomMessage {
+    pub fn event(room: &str, event: &RoomEvent) -> Self {
+        let formatted = json!({
+            "id": gen_id(),
+            "room": room,
+            "event": event,
+            "created_at": clock::now().to_rfc3339(),
+        }).to_string();
+        RoomMessage::Event(formatted)
+    }
+
+    pub async fn serialize(&self) -> String {
+        match self {
+            RoomMessage::Chat(msg) => msg.serialize().await,
+            RoomMessage::Event(formatted) => formatted.clone(),
+        }
+    }
+}
+
+// server -> client, sent by the server rather than a member
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "content")]
+pub enum RoomEvent {
+    #[serde(rename = "playback")]
+    Playback(PlaybackState),
+    #[serde(rename = "tracks")]
+    Tracks(Vec<StreamTrack>),
+    #[serde(rename = "health")]
+    Health(StreamHealth),
+    #[serde(rename = "subtitles")]
+    Subtitles(Vec<SubtitleTrack>),
+    #[serde(rename = "pong")]
+    Pong(Pong),
+    #[serde(rename = "members")]
+    Members(Vec<RoomMember>), // snapshot for a newcomer
+    #[serde(rename = "joined")]
+    Joined(RoomMember),
+    #[serde(rename = "left")]
+    Left {
+        user_id: i32,
+        name: String,
+    },
+    #[serde(rename = "closed")]
+    Closed {
+        reason: String,
+    },
+    #[serde(rename = "moderation")]
+    Moderation {
+        action: ModerationAction,
+        user_id: i32,
+        by: i32,
+        reason: Option<String>,
+    },
+    #[serde(rename = "updated")]
+    Updated {
+        name: String,
+        access: RoomAccess,
+        listed: bool,
+        capacity: usize,
+        by: i32,
+    },
+    #[serde(rename = "host_changed")]
+    HostChanged {
+        host_id: i32,
+        host_name: String,
+        by: i32,
+    },
+    #[serde(rename = "role")]
+    Role {
+        user_id: i32,
+        role: RoomRole,
+        by: i32,
+    },
+    #[serde(rename = "error")]
+    Error {
+        reason: String,
+    },
+}
+
+// a connected member as shown to the rest of the room
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomMember {
+    pub user_id:    i32,
+    pub name:       String,
+    pub role:       RoomRole,
+    pub joined_at:  DateTime<Utc>,
+    pub last_seen:  DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    Kick,
+    Ban,
+    Unban,
+    Mute,
+    Unmute,
+}
+
+// client -> server, everything on the room socket that is not a chat message
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "content")]
+pub enum RoomCommand {
+    #[serde(rename = "playback")]
+    Playback(PlaybackCommand),
+    #[serde(rename = "ping")]
+    Ping(Ping),
+    #[serde(rename = "stats")]
+    Stats(StatsReport),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action")]
+pub enum PlaybackCommand {
+    #[serde(rename = "load")]
+    Load { media_id: String },
+    #[serde(rename = "play")]
+    Play { position: Option<f64> },
+    #[serde(rename = "pause")]
+    Pause { position: Option<f64> },
+    #[serde(rename = "seek")]
+    Seek { position: f64 },
+    #[serde(rename = "rate")]
+    Rate { rate: f64 },
+    #[serde(rename = "delegate")]
+    Delegate { user_id: i32 },
+    #[serde(rename = "revoke")]
+    Revoke { user_id: i32 },
+    // an omitted track keeps the current one, `null` turns subtitles off
+    #[serde(rename = "subtitle")]
+    Subtitle {
+        #[serde(default, deserialize_with = "present")]
+        track: Option<Option<u32>>,
+        offset: Option<f64>,
+    },
+}
+
+// tells a field that is present but null apart from one that is missing
+fn present<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Option<Option<T>>, D::Error> {
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackState {
+    pub media_id:           Option<String>,
+    pub playing:            bool,
+    pub position:           f64, // seconds, as of `updated_at`
+    pub rate:               f64,
+    pub subtitle:           Option<u32>, // active subtitle track
+    pub subtitle_offset:    f64, // seconds added to every cue
+    pub updated_at:         DateTime<Utc>,
+}
+
+impl Default for PlaybackState {
+    fn default() -> Self {
+        Self {
+            media_id: None,
+            playing: false,
+            position: 0.0,
+            rate: 1.0,
+            subtitle: None,
+            subtitle_offset: 0.0,
+            updated_at: clock::now(),
+        }
+    }
+}
+
+impl PlaybackState {
+    pub const MAX_RATE: f64 = 4.0;
+    pub const MAX_SUBTITLE_OFFSET: f64 = 600.0;
+
+    // extrapolated position at `now`
+    pub fn position_at(&self, now: DateTime<Utc>) -> f64 {
+        if !self.playing { return self.position }
+        let elapsed = (now - self.updated_at).num_milliseconds() as f64 / 1000.0;
+        (self.position + elapsed * self.rate).max(0.0)
+    }
+
+    // re-anchor the state at `now`, so that later changes start from the live position
+    fn rebase(&mut self, now: DateTime<Utc>) {
+        self.position = self.position_at(now);
+        self.updated_at = now;
+    }
+
+    // delegation commands are not playback changes and are ignored here,
+    // a rejected command leaves the state untouched
+    pub fn apply(&mut self, cmd: &PlaybackCommand) -> Result<(), &'static str> {
+        let mut next = self.clone();
+        next.apply_at(cmd, clock::now())?;
+        *self = next;
+        Ok(())
+    }
+
+    fn apply_at(&mut self, cmd: &PlaybackCommand, now: DateTime<Utc>) -> Result<(), &'static str> {
+        let valid_position = |p: f64| p.is_finite() && p >= 0.0;
+        self.rebase(now);
+
+        match *cmd {
+            PlaybackCommand::Load { ref media_id } => {
+                *self = Self { media_id: Some(media_id.clone()), rate: self.rate, updated_at: now, ..Self::default() };
+            },
+            PlaybackCommand::Play { position } | PlaybackCommand::Pause { position } => {
+                if let Some(position) = position {
+                    if !valid_position(position) { return Err("Invalid position") }
+                    self.position = position;
+                }
+                self.playing = matches!(cmd, PlaybackCommand::Play { .. });
+            },
+            PlaybackCommand::Seek { position } => {
+                if !valid_position(position) { return Err("Invalid position") }
+                self.position = position;
+            },
+            PlaybackCommand::Rate { rate } => {
+                if !rate.is_finite() || rate <= 0.0 || rate > Self::MAX_RATE { return Err("Invalid rate") }
+                self.rate = rate;
+            },
+            PlaybackCommand::Subtitle { track, offset } => {
+                if self.media_id.is_none() { return Err("No media loaded") }
+                if let Some(offset) = offset {
+                    if !offset.is_finite() || offset.abs() > Self::MAX_SUBTITLE_OFFSET { return Err("Invalid subtitle offset") }
+                    self.subtitle_offset = offset;
+                }
+                if let Some(track) = track {
+                    self.subtitle = track;
+                }
+            },
+            PlaybackCommand::Delegate { .. } | PlaybackCommand::Revoke { .. } => {},
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loaded() -> PlaybackState {
+        let mut state = PlaybackState::default();
+        state.apply(&PlaybackCommand::Load { media_id: "clip".to_string() }).unwrap();
+        state
+    }
+
+    fn subtitle(json: &str) -> PlaybackCommand {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn subtitle_offset_is_bounded_either_way() {
+        // cues are shifted by the players, a negative offset shows them earlier
+        let mut state = loaded();
+        state.apply(&subtitle(r#"{"action":"subtitle","offset":-2.5}"#)).unwrap();
+        assert_eq!(state.subtitle_offset, -2.5);
+        state.apply(&subtitle(r#"{"action":"subtitle","offset":-600}"#)).unwrap();
+        assert_eq!(state.subtitle_offset, -600.0);
+
+        let err = state.apply(&subtitle(r#"{"action":"subtitle","track":3,"offset":-600.5}"#));
+        assert_eq!(err, Err("Invalid subtitle offset"));
+        assert_eq!((state.subtitle, state.subtitle_offset), (None, -600.0));
+    }
+
+    #[test]
+    fn omitted_track_keeps_it_and_null_clears_it() {
+        let mut state = loaded();
+        state.apply(&subtitle(r#"{"action":"subtitle","track":3}"#)).unwrap();
+        assert_eq!(state.subtitle, Some(3));
+        state.apply(&subtitle(r#"{"action":"subtitle","offset":1}"#)).unwrap();
+        assert_eq!(state.subtitle, Some(3));
+        state.apply(&subtitle(r#"{"action":"subtitle","track":null}"#)).unwrap();
+        assert_eq!(state.subtitle, None);
+    }
+
+    #[test]
+    fn load_resets_everything_but_the_rate() {
+        let mut state = loaded();
+        state.apply(&PlaybackCommand::Rate { rate: 1.5 }).unwrap();
+        state.apply(&PlaybackCommand::Play { position: Some(42.0) }).unwrap();
+        state.apply(&subtitle(r#"{"action":"subtitle","track":3,"offset":2}"#)).unwrap();
+
+        state.apply(&PlaybackCommand::Load { media_id: "next".to_string() }).unwrap();
+        assert_eq!(state.media_id.as_deref(), Some("next"));
+        assert_eq!((state.playing, state.position, state.rate), (false, 0.0, 1.5));
+        assert_eq!((state.subtitle, state.subtitle_offset), (None, 0.0));
+    }
+
+    #[test]
+    fn invalid_commands_change_nothing() {
+        let mut state = loaded();
+        state.apply(&PlaybackCommand::Play { position: Some(10.0) }).unwrap();
+        let before = state.clone();
+        for cmd in [
+            PlaybackCommand::Seek { position: -1.0 },
+            PlaybackCommand::Seek { position: f64::NAN },
+            PlaybackCommand::Pause { position: Some(f64::INFINITY) },
+            PlaybackCommand::Rate { rate: 0.0 },
+            PlaybackCommand::Rate { rate: PlaybackState::MAX_RATE + 0.5 },
+        ] {
+            assert!(state.apply(&cmd).is_err(), "{cmd:?}");
+            // not even re-anchored at the time of the attempt
+            assert!(state.playing);
+            assert_eq!((state.position, state.rate, state.updated_at), (before.position, before.rate, before.updated_at));
+        }
+    }
+
+    #[test]
+    fn position_follows_the_rate() {
+        let start = clock::now();
+        let later = |ms| start + chrono::Duration::milliseconds(ms);
+        let mut state = loaded();
+        state.apply_at(&PlaybackCommand::Play { position: Some(10.0) }, start).unwrap();
+        state.apply_at(&PlaybackCommand::Rate { rate: 2.0 }, start).unwrap();
+        assert_eq!(state.position_at(later(1500)), 13.0);
+
+        // the rate change re-anchors, time before it counts at the old rate
+        state.apply_at(&PlaybackCommand::Rate { rate: 0.5 }, later(1000)).unwrap();
+        assert_eq!(state.position, 12.0);
+        assert_eq!(state.position_at(later(3000)), 13.0);
+
+        state.apply_at(&PlaybackCommand::Pause { position: None }, later(3000)).unwrap();
+        assert_eq!(state.position_at(later(60_000)), 13.0);
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::role::RoomRole;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomAccess {
+    #[default]
+    Open,
+    Password,
+    Invite,
+}
+
+impl RoomAccess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoomAccess::Open => "open",
+            RoomAccess::Password => "password",
+            RoomAccess::Invite => "invite",
+        }
+    }
+
+    // unknown values fall back to the strictest mode
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "open" => RoomAccess::Open,
+            "password" => RoomAccess::Password,
+            _ => RoomAccess::Invite,
+        }
+    }
+}
+
+// the persisted part of a room, live state such as sockets stays in memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomModel {
+    pub link: String,
+    pub host_id: i32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub access: RoomAccess,
+    #[serde(skip)]
+    pub password: Option<String>, // bcrypt hash
+    pub invited: Vec<i32>,
+    pub listed: bool, // shown in the public directory
+    pub banned: Vec<i32>,
+    pub roles: Vec<(i32, RoomRole)>, // assigned roles, everyone else is a member
+    pub capacity: Option<u32>, // connected members, the server-wide ceiling when unset
+}
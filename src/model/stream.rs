@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StreamRole {
+    #[serde(rename = "publisher")]
+    Publisher,
+    #[serde(rename = "viewer")]
+    Viewer,
+}
+
+impl StreamRole {
+    pub fn is_publisher(&self) -> bool {
+        self == &StreamRole::Publisher
+    }
+}
+
+// one publisher's media as seen by viewers, the track id is the publisher's user id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StreamTrack {
+    pub publisher:  i32,
+    pub host:       bool,
+    pub mime:       Option<String>, // set while the relay is receiving this track
+}
+
+// viewer -> server, counters cover the time since the previous report
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct StatsReport {
+    pub bitrate:        f64, // kbit/s
+    pub dropped_frames: u32,
+    pub stalls:         u32,
+}
+
+impl StatsReport {
+    // far above anything a real interval produces, a report is sent every few seconds
+    const MAX_DROPPED_FRAMES: u32 = 100_000;
+    const MAX_STALLS: u32 = 10_000;
+
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if !self.bitrate.is_finite() || self.bitrate < 0.0 { return Err("Invalid bitrate") }
+        if self.dropped_frames > Self::MAX_DROPPED_FRAMES { return Err("Invalid dropped frame count") }
+        if self.stalls > Self::MAX_STALLS { return Err("Invalid stall count") }
+        Ok(())
+    }
+}
+
+// rolling summary of the viewer reports of a room
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamHealth {
+    pub window_s:           u64,
+    pub viewers:            usize, // viewers that reported within the window
+    pub struggling:         usize, // viewers that stalled recently
+    pub avg_bitrate:        f64,
+    pub avg_dropped_frames: f64,
+    pub p95_stalls:         u64,
+    pub updated_at:         DateTime<Utc>,
+}
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// a WebVTT track uploaded for one library item
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtitleTrack {
+    pub id:         u32,
+    pub media_id:   String,
+    pub label:      String,
+    pub lang:       Option<String>,
+    pub url:        String,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub vtt:        String,
+}
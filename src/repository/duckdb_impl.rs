@@ -7,11 +7,13 @@ use tokio::{sync::Mutex, time };
 use duckdb::{Connection, Result as DuckDBResult, Error as DuckDBError, Row, params};
 
 use crate::REPO_CFG;
-use crate::model::UserModel;
+use crate::model::{RecordingModel, RoomAccess, RoomModel, UserModel};
 
 use super::{RepoConfig, Repository, Error};
 use super::crud::CRUD;
 use super::user::UserRepo;
+use super::recording::RecordingRepo;
+use super::room::{RoomRepo, from_id_list, from_role_list, to_id_list, to_role_list};
 
 pub static DUCKDB_REPO: LazyLock<DuckDBRepo> =
     LazyLock::new(|| DuckDBRepo::init(&REPO_CFG).expect("Failed to init") );
@@ -58,6 +60,8 @@ impl DuckDBRepo {
 
             ret.init_schema()?;
             ret.init_user_table()?;
+            ret.init_recording_table()?;
+            ret.init_room_table()?;
 
             Ok(ret)
         } else {
@@ -88,6 +92,109 @@ impl DuckDBRepo {
             )", self.schema_name, self.schema_name),[]
         ).map(|_| ())
     }
+    
+    fn init_recording_table(&self) -> DuckDBResult<()> {
+        let conn = self.conn.try_lock().unwrap();
+        
+        conn.execute(&format!(
+            "CREATE SEQUENCE IF NOT EXISTS {}.recordings_id_seq START 1;", self.schema_name),[]
+        )?;
+        
+        conn.execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {}.recordings (
+                id          INTEGER         PRIMARY KEY DEFAULT     nextval('{}.recordings_id_seq'),
+                room_link   TEXT            NOT NULL,
+                host_id     INTEGER         NOT NULL,
+                mime        TEXT            NOT NULL,
+                path        TEXT            NOT NULL,
+                size        BIGINT          NOT NULL    DEFAULT     0,
+                started_at  TIMESTAMPTZ     NOT NULL,
+                ended_at    TIMESTAMPTZ
+            )", self.schema_name, self.schema_name),[]
+        ).map(|_| ())
+    }
+    
+    fn init_room_table(&self) -> DuckDBResult<()> {
+        let conn = self.conn.try_lock().unwrap();
+        conn.execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {}.rooms (
+                link        TEXT            PRIMARY KEY,
+                host_id     INTEGER         NOT NULL,
+                name        TEXT            NOT NULL,
+                created_at  TIMESTAMPTZ     NOT NULL,
+                access      TEXT            NOT NULL    DEFAULT 'open',
+                password    TEXT,
+                invited     TEXT            NOT NULL    DEFAULT '',
+                listed      BOOLEAN         NOT NULL    DEFAULT TRUE,
+                banned      TEXT            NOT NULL    DEFAULT '',
+                roles       TEXT            NOT NULL    DEFAULT '',
+                capacity    INTEGER
+            )", self.schema_name),[]
+        )?;
+
+        // tables created by older versions lack the later columns
+        for (column, definition) in ROOM_ADDED_COLUMNS {
+            conn.execute(&format!(
+                "ALTER TABLE {}.rooms ADD COLUMN IF NOT EXISTS {column} {definition}", self.schema_name
+            ), [])?;
+        }
+        Ok(())
+    }
+}
+
+// columns added to `rooms` after it was first released, in order;
+// duckdb cannot add NOT NULL columns, the defaults fill existing rows
+const ROOM_ADDED_COLUMNS: [(&str, &str); 7] = [
+    ("access",   "TEXT DEFAULT 'open'"),
+    ("password", "TEXT"),
+    ("invited",  "TEXT DEFAULT ''"),
+    ("listed",   "BOOLEAN DEFAULT TRUE"),
+    ("banned",   "TEXT DEFAULT ''"),
+    ("roles",    "TEXT DEFAULT ''"),
+    ("capacity", "INTEGER"),
+];
+
+// timestamps are read back as epoch milliseconds
+const RECORDING_COLUMNS: &str =
+    "id, room_link, host_id, mime, path, size, epoch_ms(started_at), epoch_ms(ended_at)";
+
+impl<'a> TryFrom<&Row<'a>> for RecordingModel {
+    type Error = DuckDBError;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            room_link: row.get(1)?,
+            host_id: row.get(2)?,
+            mime: row.get(3)?,
+            path: row.get(4)?,
+            size: row.get(5)?,
+            started_at: DateTime::from_timestamp_millis(row.get(6)?).unwrap_or(Utc::now()),
+            ended_at: row.get::<_, Option<i64>>(7)?.and_then(DateTime::from_timestamp_millis),
+        })
+    }
+}
+
+const ROOM_COLUMNS: &str = "link, host_id, name, epoch_ms(created_at), access, password, invited, listed, banned, roles, capacity";
+
+impl<'a> TryFrom<&Row<'a>> for RoomModel {
+    type Error = DuckDBError;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            link: row.get(0)?,
+            host_id: row.get(1)?,
+            name: row.get(2)?,
+            created_at: DateTime::from_timestamp_millis(row.get(3)?).unwrap_or(Utc::now()),
+            access: RoomAccess::parse(&row.get::<_, String>(4)?),
+            password: row.get(5)?,
+            invited: from_id_list(&row.get::<_, String>(6)?),
+            listed: row.get(7)?,
+            banned: from_id_list(&row.get::<_, String>(8)?),
+            roles: from_role_list(&row.get::<_, String>(9)?),
+            capacity: row.get(10)?,
+        })
+    }
 }
 
 impl<'a> TryFrom<&Row<'a>> for UserModel {
@@ -179,4 +286,126 @@ impl UserRepo for DuckDBRepo {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[async_trait::async_trait]
+impl RecordingRepo for DuckDBRepo {
+    async fn create_recording(&self, recording: RecordingModel) -> Result<RecordingModel, Error> {
+        let conn = self.conn.lock().await;
+        let recording = conn.query_row(
+            &format!("INSERT INTO {}.recordings (room_link, host_id, mime, path, size, started_at)
+                VALUES (?, ?, ?, ?, ?, CAST(? AS TIMESTAMPTZ)) RETURNING {RECORDING_COLUMNS}", self.schema_name),
+            params![
+                &recording.room_link, &recording.host_id, &recording.mime,
+                &recording.path, &recording.size, recording.started_at.to_rfc3339()
+            ],
+            |row| { RecordingModel::try_from(row) }
+        )?;
+
+        Ok(recording)
+    }
+
+    async fn finish_recording(&self, id: i32, size: i64, ended_at: DateTime<Utc>) -> Result<(), Error> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            &format!("UPDATE {}.recordings SET size = ?, ended_at = CAST(? AS TIMESTAMPTZ) WHERE id = ?", self.schema_name),
+            params![&size, ended_at.to_rfc3339(), &id]
+        )?;
+
+        Ok(())
+    }
+
+    async fn find_recording(&self, id: i32) -> Result<Option<RecordingModel>, Error> {
+        let conn = self.conn.lock().await;
+        let res = conn.query_row(
+            &format!("SELECT {RECORDING_COLUMNS} FROM {}.recordings WHERE id = ?", self.schema_name),
+            [id], |row| { RecordingModel::try_from(row) }
+        );
+        match res {
+            Ok(recording) => Ok(Some(recording)),
+            Err(DuckDBError::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn find_recordings_by_host(&self, host_id: i32) -> Result<Vec<RecordingModel>, Error> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {RECORDING_COLUMNS} FROM {}.recordings WHERE host_id = ? ORDER BY started_at DESC", self.schema_name
+        ))?;
+        let rows = stmt.query_map([host_id], |row| { RecordingModel::try_from(row) })?;
+        Ok(rows.filter_map(|row| row.ok()).collect())
+    }
+
+    async fn find_recordings_by_room(&self, room_link: &str) -> Result<Vec<RecordingModel>, Error> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {RECORDING_COLUMNS} FROM {}.recordings WHERE room_link = ? ORDER BY started_at DESC", self.schema_name
+        ))?;
+        let rows = stmt.query_map([room_link], |row| { RecordingModel::try_from(row) })?;
+        Ok(rows.filter_map(|row| row.ok()).collect())
+    }
+
+    async fn find_unfinished_recordings(&self) -> Result<Vec<RecordingModel>, Error> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {RECORDING_COLUMNS} FROM {}.recordings WHERE ended_at IS NULL", self.schema_name
+        ))?;
+        let rows = stmt.query_map([], |row| { RecordingModel::try_from(row) })?;
+        Ok(rows.filter_map(|row| row.ok()).collect())
+    }
+
+    async fn delete_recording(&self, id: i32) -> Result<bool, Error> {
+        let conn = self.conn.lock().await;
+        let deleted = conn.execute(&format!("DELETE FROM {}.recordings WHERE id = ?", self.schema_name), [id])?;
+        Ok(deleted > 0)
+    }
+}
+
+#[async_trait::async_trait]
+impl RoomRepo for DuckDBRepo {
+    async fn create_room(&self, room: RoomModel) -> Result<(), Error> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            &format!("INSERT INTO {}.rooms (link, host_id, name, created_at, access, password, invited, listed, banned, roles, capacity)
+                VALUES (?, ?, ?, CAST(? AS TIMESTAMPTZ), ?, ?, ?, ?, ?, ?, ?)", self.schema_name),
+            params![
+                &room.link, &room.host_id, &room.name, room.created_at.to_rfc3339(),
+                room.access.as_str(), &room.password,
+                to_id_list(&room.invited), &room.listed, to_id_list(&room.banned),
+                to_role_list(&room.roles), &room.capacity
+            ]
+        )?;
+
+        Ok(())
+    }
+
+    async fn find_rooms(&self) -> Result<Vec<RoomModel>, Error> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ROOM_COLUMNS} FROM {}.rooms ORDER BY created_at", self.schema_name
+        ))?;
+        let rows = stmt.query_map([], |row| { RoomModel::try_from(row) })?;
+        Ok(rows.filter_map(|row| row.inspect_err(|e| eprintln!("Room row skipped: {e}")).ok()).collect())
+    }
+
+    async fn update_room(&self, room: RoomModel) -> Result<(), Error> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            &format!("UPDATE {}.rooms SET host_id = ?, name = ?, access = ?, password = ?, invited = ?, listed = ?, banned = ?, roles = ?, capacity = ?
+                WHERE link = ?", self.schema_name),
+            params![
+                &room.host_id, &room.name, room.access.as_str(), &room.password,
+                to_id_list(&room.invited), &room.listed, to_id_list(&room.banned), to_role_list(&room.roles), &room.capacity, &room.link
+            ]
+        )?;
+
+        Ok(())
+    }
+
+    async fn delete_room(&self, link: &str) -> Result<bool, Error> {
+        let conn = self.conn.lock().await;
+        let deleted = conn.execute(&format!("DELETE FROM {}.rooms WHERE link = ?", self.schema_name), [link])?;
+        Ok(deleted > 0)
+    }
+}
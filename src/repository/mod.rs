@@ -1,4 +1,6 @@
 mod user;
+mod recording;
+mod room;
 mod crud;
 mod config;
 mod error;
@@ -11,13 +13,15 @@ pub use crud::CRUD;
 pub use error::Error;
 pub use config::RepoConfig;
 pub use user::UserRepo;
+pub use recording::RecordingRepo;
+pub use room::RoomRepo;
 #[cfg(feature = "repo_duckdb")]
 pub use duckdb_impl::{ DuckDBRepo as Repo, DUCKDB_REPO as REPO };
 #[cfg(feature = "repo_sqlite")]
 pub use sqlite_impl::{ SqliteRepo as Repo, SQLITE_REPO as REPO };
 
 #[async_trait::async_trait]
-pub trait Repository: UserRepo + Send + Sync {
+pub trait Repository: UserRepo + RecordingRepo + RoomRepo + Send + Sync {
     async fn conn() -> Self where Self: Sized;
     async fn clone(&self) -> Self where Self: Sized;
     
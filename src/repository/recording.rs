@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use crate::model::RecordingModel;
+use crate::repository::Error;
+
+#[async_trait::async_trait]
+pub trait RecordingRepo {
+    async fn create_recording(&self, recording: RecordingModel) -> Result<RecordingModel, Error>;
+    async fn finish_recording(&self, id: i32, size: i64, ended_at: DateTime<Utc>) -> Result<(), Error>;
+    async fn find_recording(&self, id: i32) -> Result<Option<RecordingModel>, Error>;
+    async fn find_recordings_by_host(&self, host_id: i32) -> Result<Vec<RecordingModel>, Error>;
+    async fn find_recordings_by_room(&self, room_link: &str) -> Result<Vec<RecordingModel>, Error>;
+    async fn find_unfinished_recordings(&self) -> Result<Vec<RecordingModel>, Error>;
+    async fn delete_recording(&self, id: i32) -> Result<bool, Error>;
+}
@@ -0,0 +1,33 @@
+use crate::model::{RoomModel, RoomRole};
+use crate::repository::Error;
+
+#[async_trait::async_trait]
+pub trait RoomRepo {
+    async fn create_room(&self, room: RoomModel) -> Result<(), Error>;
+    async fn find_rooms(&self) -> Result<Vec<RoomModel>, Error>;
+    async fn update_room(&self, room: RoomModel) -> Result<(), Error>;
+    async fn delete_room(&self, link: &str) -> Result<bool, Error>;
+}
+
+// user id lists are stored comma separated
+pub fn to_id_list(ids: &[i32]) -> String {
+    ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")
+}
+
+pub fn from_id_list(ids: &str) -> Vec<i32> {
+    ids.split(',').filter_map(|id| id.trim().parse().ok()).collect()
+}
+
+// `id:role` pairs, comma separated
+pub fn to_role_list(roles: &[(i32, RoomRole)]) -> String {
+    roles.iter().map(|(id, role)| format!("{id}:{}", role.as_str())).collect::<Vec<_>>().join(",")
+}
+
+pub fn from_role_list(roles: &str) -> Vec<(i32, RoomRole)> {
+    roles.split(',')
+        .filter_map(|pair| {
+            let (id, role) = pair.trim().split_once(':')?;
+            Some((id.parse().ok()?, RoomRole::parse(role)?))
+        })
+        .collect()
+}
@@ -2,16 +2,18 @@ use std::sync::{ Arc, LazyLock };
 use std::time::Duration;
 
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use tokio::{sync::Mutex, time };
 use rusqlite::{Connection, Result as SqliteResult, Error as SqliteError, Row, params};
 
 use crate::REPO_CFG;
-use crate::model::UserModel;
+use crate::model::{RecordingModel, RoomAccess, RoomModel, UserModel};
 
 use super::{RepoConfig, Repository, Error};
 use super::crud::CRUD;
 use super::user::UserRepo;
+use super::recording::RecordingRepo;
+use super::room::{RoomRepo, from_id_list, from_role_list, to_id_list, to_role_list};
 
 pub static SQLITE_REPO: LazyLock<SqliteRepo> =
     LazyLock::new(|| SqliteRepo::init(&REPO_CFG).expect("Failed to init") );
@@ -48,6 +50,8 @@ impl SqliteRepo {
             let ret = Self::new(conn);
 
             ret.init_user_table()?;
+            ret.init_recording_table()?;
+            ret.init_room_table()?;
 
             Ok(ret)
         } else {
@@ -69,6 +73,110 @@ impl SqliteRepo {
             )",[]
         ).map(|_| ())
     }
+
+    fn init_recording_table(&self) -> SqliteResult<()> {
+        let conn = self.conn.try_lock().unwrap();
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS recordings (
+                id          INTEGER         PRIMARY KEY AUTOINCREMENT,
+                room_link   TEXT            NOT NULL,
+                host_id     INTEGER         NOT NULL,
+                mime        TEXT            NOT NULL,
+                path        TEXT            NOT NULL,
+                size        INTEGER         NOT NULL    DEFAULT 0,
+                started_at  TEXT            NOT NULL,
+                ended_at    TEXT
+            )",[]
+        ).map(|_| ())
+    }
+
+    fn init_room_table(&self) -> SqliteResult<()> {
+        let conn = self.conn.try_lock().unwrap();
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS rooms (
+                link        TEXT            PRIMARY KEY,
+                host_id     INTEGER         NOT NULL,
+                name        TEXT            NOT NULL,
+                created_at  TEXT            NOT NULL,
+                access      TEXT            NOT NULL    DEFAULT 'open',
+                password    TEXT,
+                invited     TEXT            NOT NULL    DEFAULT '',
+                listed      INTEGER         NOT NULL    DEFAULT 1,
+                banned      TEXT            NOT NULL    DEFAULT '',
+                roles       TEXT            NOT NULL    DEFAULT '',
+                capacity    INTEGER
+            )",[]
+        )?;
+
+        // tables created by older versions lack the later columns
+        let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('rooms')")?;
+        let columns: Vec<String> = stmt.query_map([], |row| row.get(0))?.collect::<SqliteResult<_>>()?;
+        for (column, definition) in ROOM_ADDED_COLUMNS {
+            if columns.iter().any(|c| c == column) { continue }
+            conn.execute(&format!("ALTER TABLE rooms ADD COLUMN {column} {definition}"), [])?;
+        }
+        Ok(())
+    }
+}
+
+// columns added to `rooms` after it was first released, in order
+const ROOM_ADDED_COLUMNS: [(&str, &str); 7] = [
+    ("access",   "TEXT NOT NULL DEFAULT 'open'"),
+    ("password", "TEXT"),
+    ("invited",  "TEXT NOT NULL DEFAULT ''"),
+    ("listed",   "INTEGER NOT NULL DEFAULT 1"),
+    ("banned",   "TEXT NOT NULL DEFAULT ''"),
+    ("roles",    "TEXT NOT NULL DEFAULT ''"),
+    ("capacity", "INTEGER"),
+];
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+fn to_sql_time(time: &DateTime<Utc>) -> String {
+    time.format(TIME_FORMAT).to_string()
+}
+
+fn from_sql_time(time: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(time, TIME_FORMAT).ok().map(|t| t.and_utc())
+}
+
+impl<'a> TryFrom<&Row<'a>> for RecordingModel {
+    type Error = SqliteError;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            room_link: row.get(1)?,
+            host_id: row.get(2)?,
+            mime: row.get(3)?,
+            path: row.get(4)?,
+            size: row.get(5)?,
+            started_at: from_sql_time(&row.get::<_, String>(6)?).unwrap_or(Utc::now()),
+            ended_at: row.get::<_, Option<String>>(7)?.as_deref().and_then(from_sql_time),
+        })
+    }
+}
+
+impl<'a> TryFrom<&Row<'a>> for RoomModel {
+    type Error = SqliteError;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            link: row.get(0)?,
+            host_id: row.get(1)?,
+            name: row.get(2)?,
+            created_at: from_sql_time(&row.get::<_, String>(3)?).unwrap_or(Utc::now()),
+            access: RoomAccess::parse(&row.get::<_, String>(4)?),
+            password: row.get(5)?,
+            invited: from_id_list(&row.get::<_, String>(6)?),
+            listed: row.get(7)?,
+            banned: from_id_list(&row.get::<_, String>(8)?),
+            roles: from_role_list(&row.get::<_, String>(9)?),
+            capacity: row.get(10)?,
+        })
+    }
 }
 
 impl<'a> TryFrom<&Row<'a>> for UserModel {
@@ -166,4 +274,117 @@ impl UserRepo for SqliteRepo {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[async_trait::async_trait]
+impl RecordingRepo for SqliteRepo {
+    async fn create_recording(&self, recording: RecordingModel) -> Result<RecordingModel, Error> {
+        let conn = self.conn.lock().await;
+        let recording = conn.query_row(
+            "INSERT INTO recordings (room_link, host_id, mime, path, size, started_at)
+                VALUES (?, ?, ?, ?, ?, ?) RETURNING *",
+            params![
+                &recording.room_link, &recording.host_id, &recording.mime,
+                &recording.path, &recording.size, to_sql_time(&recording.started_at)
+            ],
+            |row| { RecordingModel::try_from(row) }
+        )?;
+
+        Ok(recording)
+    }
+
+    async fn finish_recording(&self, id: i32, size: i64, ended_at: DateTime<Utc>) -> Result<(), Error> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "UPDATE recordings SET size = ?, ended_at = ? WHERE id = ?",
+            params![&size, to_sql_time(&ended_at), &id]
+        )?;
+
+        Ok(())
+    }
+
+    async fn find_recording(&self, id: i32) -> Result<Option<RecordingModel>, Error> {
+        let conn = self.conn.lock().await;
+        let res = conn.query_row(
+            "SELECT * FROM recordings WHERE id = ?",
+            [id], |row| { RecordingModel::try_from(row) }
+        );
+        match res {
+            Ok(recording) => Ok(Some(recording)),
+            Err(SqliteError::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn find_recordings_by_host(&self, host_id: i32) -> Result<Vec<RecordingModel>, Error> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare("SELECT * FROM recordings WHERE host_id = ? ORDER BY started_at DESC")?;
+        let rows = stmt.query_map([host_id], |row| { RecordingModel::try_from(row) })?;
+        Ok(rows.filter_map(|row| row.ok()).collect())
+    }
+
+    async fn find_recordings_by_room(&self, room_link: &str) -> Result<Vec<RecordingModel>, Error> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare("SELECT * FROM recordings WHERE room_link = ? ORDER BY started_at DESC")?;
+        let rows = stmt.query_map([room_link], |row| { RecordingModel::try_from(row) })?;
+        Ok(rows.filter_map(|row| row.ok()).collect())
+    }
+
+    async fn find_unfinished_recordings(&self) -> Result<Vec<RecordingModel>, Error> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare("SELECT * FROM recordings WHERE ended_at IS NULL")?;
+        let rows = stmt.query_map([], |row| { RecordingModel::try_from(row) })?;
+        Ok(rows.filter_map(|row| row.ok()).collect())
+    }
+
+    async fn delete_recording(&self, id: i32) -> Result<bool, Error> {
+        let conn = self.conn.lock().await;
+        let deleted = conn.execute("DELETE FROM recordings WHERE id = ?", [id])?;
+        Ok(deleted > 0)
+    }
+}
+
+#[async_trait::async_trait]
+impl RoomRepo for SqliteRepo {
+    async fn create_room(&self, room: RoomModel) -> Result<(), Error> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "INSERT INTO rooms (link, host_id, name, created_at, access, password, invited, listed, banned, roles, capacity)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                &room.link, &room.host_id, &room.name, to_sql_time(&room.created_at),
+                room.access.as_str(), &room.password, to_id_list(&room.invited), &room.listed, to_id_list(&room.banned),
+                to_role_list(&room.roles), &room.capacity
+            ]
+        )?;
+
+        Ok(())
+    }
+
+    async fn find_rooms(&self) -> Result<Vec<RoomModel>, Error> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare("SELECT link, host_id, name, created_at, access, password, invited, listed, banned, roles, capacity FROM rooms ORDER BY created_at")?;
+        let rows = stmt.query_map([], |row| { RoomModel::try_from(row) })?;
+        Ok(rows.filter_map(|row| row.inspect_err(|e| eprintln!("Room row skipped: {e}")).ok()).collect())
+    }
+
+    async fn update_room(&self, room: RoomModel) -> Result<(), Error> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "UPDATE rooms SET host_id = ?, name = ?, access = ?, password = ?, invited = ?, listed = ?, banned = ?, roles = ?, capacity = ?
+                WHERE link = ?",
+            params![
+                &room.host_id, &room.name, room.access.as_str(), &room.password,
+                to_id_list(&room.invited), &room.listed, to_id_list(&room.banned), to_role_list(&room.roles), &room.capacity, &room.link
+            ]
+        )?;
+
+        Ok(())
+    }
+
+    async fn delete_room(&self, link: &str) -> Result<bool, Error> {
+        let conn = self.conn.lock().await;
+        let deleted = conn.execute("DELETE FROM rooms WHERE link = ?", [link])?;
+        Ok(deleted > 0)
+    }
+}
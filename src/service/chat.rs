@@ -1,15 +1,19 @@
 use thiserror::Error as ThisError;
 
 use std::sync::Arc;
-use axum::extract::ws::{Message, WebSocket};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
 use futures::{SinkExt, StreamExt};
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
+use crate::clock;
 use crate::controller::Response;
 use super::{room, user, Repository};
-use crate::model::{ChatMessage, ChatMessageContent};
+use crate::model::{ChatMessage, ChatMessageContent, CloseCode, RoomCommand, RoomEvent, RoomMessage};
 
-const MPSC_BUF_SIZE: usize = 32;
+// messages are small and a full queue hangs up, so bursts get plenty of headroom
+const MPSC_BUF_SIZE: usize = 1024;
 
 #[derive(ThisError, Debug)]
 pub enum ChatError {
@@ -40,26 +44,61 @@ pub async fn handle_websocket(
     user_id: i32, repo: Arc<dyn Repository>
 ) -> Result<(), ChatError> {
     let user = user::get_user_by_id(repo, user_id).await?;
-    let (tx, mut rx) = mpsc::channel::<Arc<ChatMessage>>(MPSC_BUF_SIZE);
+    let (tx, mut rx) = mpsc::channel::<Arc<RoomMessage>>(MPSC_BUF_SIZE);
     let (mut sender, mut recver) = socket.split();
 
     let room = room::get_room_by_link(&room_link)?;
-    room.join(user_id, tx.clone()).await;
+    let (conn, mut hangup) = match room.join(user_id, user.name.clone(), tx.clone()).await {
+        Ok(joined) => joined,
+        Err((code, reason)) => {
+            let frame = CloseFrame { code: code as u16, reason: reason.into() };
+            sender.send(Message::Close(Some(frame))).await?;
+            return Ok(());
+        }
+    };
+    let joined = room.clone();
     
     println!("CurrRooms: {:?}", room::rooms());
     
+    // ms of the last frame from this socket, read by the heartbeat in the send loop
+    let last_frame = Arc::new(AtomicI64::new(clock::now_ms() as i64));
+    let _last_frame = last_frame.clone();
     let _tx = tx.clone();
     let recv_fut = async move {
         while let Some(Ok(msg)) = recver.next().await {
+            let received_at = clock::now_ms();
+            _last_frame.store(received_at as i64, Ordering::Relaxed);
+            room.seen(user.id);
             println!("recv: {:?}", msg);
             if let Message::Text(text) = msg {
                 if let Ok(content) = serde_json::from_str::<ChatMessageContent>(&text) {
-                    room.sync_message(user.id, content).await?;
+                    // muted members are told, everyone else never sees the message
+                    if let Err(e) = room.sync_message(user.id, Some(conn), content).await {
+                        let event = RoomEvent::Error { reason: e.to_string() };
+                        _tx.send(Arc::new(RoomMessage::event(&room_link, &event))).await
+                            .map_err(|_| ChatError::InternalError)?;
+                    }
+                } else if let Ok(command) = serde_json::from_str::<RoomCommand>(&text) {
+                    let res = match command {
+                        RoomCommand::Playback(cmd) => room.control_playback(user.id, cmd).await,
+                        RoomCommand::Stats(report) => room.report_stats(user.id, report).await,
+                        RoomCommand::Ping(ping) => {
+                            let event = RoomEvent::Pong(ping.pong(received_at));
+                            _tx.send(Arc::new(RoomMessage::event(&room_link, &event))).await
+                                .map_err(|_| ChatError::InternalError)?;
+                            Ok(())
+                        },
+                    };
+                    if let Err(e) = res {
+                        let event = RoomEvent::Error { reason: e.to_string() };
+                        _tx.send(Arc::new(RoomMessage::event(&room_link, &event))).await
+                            .map_err(|_| ChatError::InternalError)?;
+                    }
                 } else {
                     // TODO! 
                     println!("bad message: {}", text);
-                    _tx.send(Arc::new(ChatMessage::new(user.id, room_link.clone(), 
-                        ChatMessageContent::Text("发的不对你这个".to_string())))
+                    _tx.send(Arc::new(RoomMessage::Chat(ChatMessage::new(user.id, room_link.clone(), 
+                        ChatMessageContent::Text("发的不对你这个".to_string()))))
                     ).await.map_err(|_| ChatError::InternalError)?;
                 }
             }
@@ -67,10 +106,39 @@ pub async fn handle_websocket(
         Ok(())
     };
     
+    let cfg = &crate::ROOM_CFG;
     let send_fut = async move {
-        while let Some(msg) = rx.recv().await {
-            println!("sending: {:?}", msg);
-            sender.send(msg.serialize().await.into()).await.map_err(|e| ChatError::from(e))?;
+        let timeout = Duration::from_secs(cfg.heartbeat_timeout);
+        let mut heartbeat = tokio::time::interval(Duration::from_secs(cfg.heartbeat_interval));
+        heartbeat.tick().await;
+        let close = loop {
+            let msg = tokio::select! {
+                biased;
+                // kicked, banned, the room closed or the queue overflowed
+                Ok(()) = hangup.changed() => break hangup.borrow_and_update().clone(),
+                msg = rx.recv() => {
+                    let Some(msg) = msg else { break None };
+                    println!("sending: {:?}", msg);
+                    msg.serialize().await.into()
+                }
+                _ = heartbeat.tick() => {
+                    // browsers answer pings on their own, silence means the peer is gone
+                    let silent_ms = clock::now_ms() as i64 - last_frame.load(Ordering::Relaxed);
+                    if silent_ms >= cfg.heartbeat_timeout as i64 * 1000 {
+                        break Some((CloseCode::Timeout, format!("No response for {}s", silent_ms / 1000)));
+                    }
+                    Message::Ping(Default::default())
+                }
+            };
+            // a peer that stops reading blocks the write, not the heartbeat
+            match tokio::time::timeout(timeout, sender.send(msg)).await {
+                Ok(res) => res.map_err(ChatError::from)?,
+                Err(_) => break Some((CloseCode::Timeout, "Write timed out".to_string())),
+            }
+        };
+        if let Some((code, reason)) = close {
+            let frame = CloseFrame { code: code as u16, reason: reason.into() };
+            let _ = tokio::time::timeout(timeout, sender.send(Message::Close(Some(frame)))).await;
         }
         Ok(())
     };
@@ -86,6 +154,9 @@ pub async fn handle_websocket(
             recv_task.abort();
         }
     }
+    // whichever side ended first, the connection is dropped here,
+    // already gone if kicked or the room closed
+    let _ = joined.leave(user_id, conn).await;
         
     Ok(())
 }
@@ -100,7 +171,7 @@ pub async fn handle_websocket(
 pub async fn send_message(repo: Arc<dyn Repository>, user_id: i32, room_link: &str, content: String) -> Result<(), ChatError> {
     let user = user::get_user_by_id(repo, user_id).await?;
     let room = room::get_room_by_link(room_link)?;
-    room.sync_message(user.id, ChatMessageContent::Text(content)).await?;
+    room.sync_message(user.id, None, ChatMessageContent::Text(content)).await?;
     Ok(())
 }
 
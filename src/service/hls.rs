@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Instant;
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use thiserror::Error as ThisError;
+
+use crate::controller::Response;
+use super::{mp4, relay, room};
+
+const HLS_WINDOW_SIZE: usize = 6;
+const MAX_SEGMENT_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(ThisError, Debug)]
+pub enum HlsError {
+    #[error("{0}")]
+    RoomError(#[from] room::RoomError),
+
+    #[error("Not found")]
+    NotFound,
+}
+
+impl From<HlsError> for Response {
+    fn from(e: HlsError) -> Self {
+        match e {
+            HlsError::RoomError(e) => e.into(),
+            HlsError::NotFound => Response::code(StatusCode::NOT_FOUND),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HlsFile {
+    Playlist,
+    Init(u64),
+    Segment(u64),
+}
+
+impl FromStr for HlsFile {
+    type Err = HlsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "index.m3u8" { return Ok(HlsFile::Playlist) }
+        if let Some(generation) = s.strip_prefix("init-").and_then(|s| s.strip_suffix(".mp4")) {
+            return generation.parse().map(HlsFile::Init).map_err(|_| HlsError::NotFound);
+        }
+        s.strip_suffix(".m4s")
+            .and_then(|seq| seq.parse().ok())
+            .map(HlsFile::Segment)
+            .ok_or(HlsError::NotFound)
+    }
+}
+
+pub struct HlsPayload {
+    pub content_type:   &'static str,
+    pub immutable:      bool,
+    pub body:           Bytes,
+}
+
+#[derive(Debug)]
+struct HlsSegment {
+    seq:        u64,
+    generation: u64, // of the init segment it decodes with
+    duration:   f64,
+    data:       Bytes,
+}
+
+#[derive(Debug)]
+struct PendingSegment {
+    chunks:     Vec<Bytes>,
+    size:       usize,
+    decode_ts:  Option<u64>,
+    started_at: Instant,
+}
+
+// sliding window of keyframe groups, only fed by fMP4 streams
+#[derive(Debug, Default)]
+pub struct HlsWindow {
+    enabled:    bool,
+    ended:      bool,
+    generation: u64,
+    timescale:  Option<u32>,
+    init:       Option<Bytes>,
+    old_inits:  Vec<(u64, Bytes)>, // generation -> init, while segments in the window need them
+    pending:    Option<PendingSegment>,
+    segments:   VecDeque<HlsSegment>,
+    next_seq:   u64, // keeps growing across restarts so segment urls never change meaning
+    discontinuity_seq:  u64, // generation changes that have left the window
+    target_duration:    u64, // may only grow while the playlist is live
+}
+
+impl HlsWindow {
+    pub fn reset(&mut self, mime: &str) {
+        self.enabled = mime.starts_with("video/mp4") || mime.starts_with("audio/mp4");
+        self.ended = false;
+        self.init = None;
+        self.old_inits.clear();
+        self.timescale = None;
+        self.pending = None;
+        self.segments.clear();
+    }
+
+    // segments already in the window keep their own init, the playlist marks the switch
+    pub fn push_init(&mut self, data: &Bytes) {
+        if !self.enabled { return }
+        let generation = self.generation;
+        if let Some(old) = self.init.replace(data.clone())
+            && self.segments.iter().any(|s| s.generation == generation) {
+            self.old_inits.push((generation, old));
+        }
+        self.generation += 1;
+        self.timescale = mp4::timescale(data);
+        self.pending = None;
+    }
+
+    pub fn push_chunk(&mut self, key: bool, data: &Bytes) {
+        if !self.enabled || self.init.is_none() { return }
+
+        let decode_ts = if key { mp4::decode_time(data) } else { None };
+        if key {
+            self.flush(decode_ts);
+            self.pending = Some(PendingSegment {
+                chunks: vec![],
+                size: 0,
+                decode_ts,
+                started_at: Instant::now(),
+            });
+        }
+
+        let Some(pending) = self.pending.as_mut() else { return };
+        if pending.size + data.len() > MAX_SEGMENT_BYTES {
+            self.pending = None;
+            return;
+        }
+        pending.size += data.len();
+        pending.chunks.push(data.clone());
+    }
+
+    pub fn finish(&mut self) {
+        if !self.enabled { return }
+        self.flush(None);
+        self.ended = true;
+    }
+
+    // close the pending group, preferring fragment decode times over wall clock
+    fn flush(&mut self, next_decode_ts: Option<u64>) {
+        let Some(pending) = self.pending.take() else { return };
+
+        let duration = match (pending.decode_ts, next_decode_ts, self.timescale) {
+            (Some(start), Some(end), Some(scale)) if end > start && scale > 0 =>
+                (end - start) as f64 / scale as f64,
+            _ => pending.started_at.elapsed().as_secs_f64(),
+        };
+
+        self.segments.push_back(HlsSegment {
+            seq: self.next_seq,
+            generation: self.generation,
+            duration,
+            data: pending.chunks.concat().into(),
+        });
+        self.next_seq += 1;
+        self.target_duration = self.target_duration.max(duration.ceil() as u64).max(1);
+
+        while self.segments.len() > HLS_WINDOW_SIZE {
+            let Some(dropped) = self.segments.pop_front() else { break };
+            if self.segments.front().is_some_and(|s| s.generation != dropped.generation) {
+                self.discontinuity_seq += 1;
+            }
+        }
+        let segments = &self.segments;
+        self.old_inits.retain(|(generation, _)| segments.iter().any(|s| s.generation == *generation));
+    }
+
+    fn playlist(&self) -> Option<String> {
+        if !self.enabled || self.init.is_none() || self.segments.is_empty() { return None }
+
+        let mut ret = String::new();
+        let _ = writeln!(ret, "#EXTM3U");
+        let _ = writeln!(ret, "#EXT-X-VERSION:7");
+        let _ = writeln!(ret, "#EXT-X-INDEPENDENT-SEGMENTS");
+        let _ = writeln!(ret, "#EXT-X-TARGETDURATION:{}", self.target_duration);
+        let _ = writeln!(ret, "#EXT-X-MEDIA-SEQUENCE:{}", self.segments[0].seq);
+        let _ = writeln!(ret, "#EXT-X-DISCONTINUITY-SEQUENCE:{}", self.discontinuity_seq);
+        let mut generation = None;
+        for segment in &self.segments {
+            // an encoder restart brings a new init segment
+            if generation != Some(segment.generation) {
+                if generation.is_some() {
+                    let _ = writeln!(ret, "#EXT-X-DISCONTINUITY");
+                }
+                let _ = writeln!(ret, "#EXT-X-MAP:URI=\"init-{}.mp4\"", segment.generation);
+                generation = Some(segment.generation);
+            }
+            let _ = writeln!(ret, "#EXTINF:{:.3},", segment.duration);
+            let _ = writeln!(ret, "{}.m4s", segment.seq);
+        }
+        if self.ended {
+            let _ = writeln!(ret, "#EXT-X-ENDLIST");
+        }
+        Some(ret)
+    }
+
+    fn file(&self, file: HlsFile) -> Option<HlsPayload> {
+        match file {
+            HlsFile::Playlist => self.playlist().map(|p| HlsPayload {
+                content_type: "application/vnd.apple.mpegurl",
+                immutable: false,
+                body: p.into(),
+            }),
+            HlsFile::Init(generation) => self.init.clone()
+                .filter(|_| generation == self.generation)
+                .or_else(|| self.old_inits.iter().find(|(g, _)| *g == generation).map(|(_, init)| init.clone()))
+                .map(|body| HlsPayload { content_type: "video/mp4", immutable: true, body }),
+            HlsFile::Segment(seq) => self.segments.iter()
+                .find(|s| s.seq == seq)
+                .map(|s| HlsPayload { content_type: "video/iso.segment", immutable: true, body: s.data.clone() }),
+        }
+    }
+}
+
+// only the host's track is packaged
+pub fn get_file(room_link: &str, user_id: i32, file: HlsFile) -> Result<HlsPayload, HlsError> {
+    let room = room::get_room_by_link(room_link)?;
+    room.stream_role(user_id)?;
+    relay::with_hls(room_link, room.host_id(), |hls| hls.file(file))
+        .flatten()
+        .ok_or(HlsError::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `keys` keyframe groups per init, the group still pending at a restart is dropped
+    fn window(inits: &[&'static [u8]], keys: usize) -> HlsWindow {
+        let mut hls = HlsWindow::default();
+        hls.reset("video/mp4");
+        for init in inits {
+            hls.push_init(&Bytes::from_static(init));
+            for _ in 0..keys {
+                hls.push_chunk(true, &Bytes::from_static(b"key"));
+            }
+        }
+        hls.finish();
+        hls
+    }
+
+    #[test]
+    fn restart_is_marked_as_a_discontinuity() {
+        let playlist = window(&[b"first", b"second"], 2).playlist().unwrap();
+        let lines: Vec<&str> = playlist.lines().filter(|l| !l.starts_with("#EXTINF")).collect();
+        assert_eq!(lines[6..], [
+            "#EXT-X-MAP:URI=\"init-1.mp4\"", "0.m4s",
+            "#EXT-X-DISCONTINUITY",
+            "#EXT-X-MAP:URI=\"init-2.mp4\"", "1.m4s", "2.m4s",
+            "#EXT-X-ENDLIST",
+        ]);
+        assert!(playlist.contains("#EXT-X-DISCONTINUITY-SEQUENCE:0\n"));
+    }
+
+    #[test]
+    fn old_init_is_served_while_its_segments_are_listed() {
+        let hls = window(&[b"first", b"second"], 2);
+        assert_eq!(hls.file(HlsFile::Init(1)).unwrap().body, "first");
+        assert_eq!(hls.file(HlsFile::Init(2)).unwrap().body, "second");
+    }
+
+    #[test]
+    fn restart_leaving_the_window_bumps_the_discontinuity_sequence() {
+        let hls = window(&[b"first", b"second"], HLS_WINDOW_SIZE + 1);
+        let playlist = hls.playlist().unwrap();
+        assert!(playlist.contains("#EXT-X-DISCONTINUITY-SEQUENCE:1\n"));
+        assert!(!playlist.contains("#EXT-X-DISCONTINUITY\n"));
+        assert!(hls.file(HlsFile::Init(1)).is_none());
+    }
+}
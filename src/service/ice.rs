@@ -0,0 +1,57 @@
+use base64::Engine;
+use chrono::Utc;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha1::Sha1;
+
+use crate::ICE_CFG;
+
+#[derive(Clone, Debug)]
+pub struct IceConfig {
+    pub stun_urls:      &'static [&'static str],
+    pub turn_urls:      &'static [&'static str],
+    pub turn_secret:    &'static str, // shared with the TURN server (`static-auth-secret`)
+    pub credential_ttl: i64, // seconds
+}
+
+// shaped like the browser's `RTCIceServer`
+#[derive(Debug, Serialize)]
+pub struct IceServer {
+    pub urls:       Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username:   Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+fn urls(urls: &[&str]) -> Vec<String> {
+    urls.iter().map(|url| url.to_string()).collect()
+}
+
+// TURN REST API scheme: username is `expiry:user`, password is base64(HMAC-SHA1(secret, username))
+fn turn_credential(user_id: i32, expires_at: i64) -> (String, String) {
+    let username = format!("{expires_at}:{user_id}");
+    let mut mac = Hmac::<Sha1>::new_from_slice(ICE_CFG.turn_secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(username.as_bytes());
+    (username, BASE64.encode(mac.finalize().into_bytes()))
+}
+
+// STUN servers as configured, TURN servers with credentials minted for this user
+pub fn servers(user_id: i32) -> (Vec<IceServer>, i64) {
+    let mut ret = vec![];
+    if !ICE_CFG.stun_urls.is_empty() {
+        ret.push(IceServer { urls: urls(ICE_CFG.stun_urls), username: None, credential: None });
+    }
+    if !ICE_CFG.turn_urls.is_empty() {
+        // the TURN server checks the expiry against its own wall clock
+        let (username, credential) = turn_credential(user_id, Utc::now().timestamp() + ICE_CFG.credential_ttl);
+        ret.push(IceServer {
+            urls: urls(ICE_CFG.turn_urls),
+            username: Some(username),
+            credential: Some(credential),
+        });
+    }
+    (ret, ICE_CFG.credential_ttl)
+}
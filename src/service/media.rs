@@ -0,0 +1,213 @@
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use thiserror::Error as ThisError;
+
+use crate::controller::Response;
+use super::mp4;
+
+const MAX_MOOV_BYTES: u64 = 64 * 1024 * 1024;
+
+static LIBRARY: LazyLock<DashMap<String, MediaItem>> = LazyLock::new(DashMap::new); // id -> item
+
+#[derive(Clone, Debug)]
+pub struct MediaConfig {
+    pub root: &'static str,
+}
+
+#[derive(ThisError, Debug)]
+pub enum MediaError {
+    #[error("Media not found")]
+    MediaNotFound,
+}
+
+impl From<MediaError> for Response {
+    fn from(e: MediaError) -> Self {
+        Response::error(&e.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MediaItem {
+    pub id:             String,
+    pub name:           String, // path relative to the library root
+    pub size:           u64,
+    pub mime:           &'static str,
+    pub duration:       Option<f64>,
+    pub modified_at:    DateTime<Utc>,
+    #[serde(skip)]
+    pub path:           PathBuf,
+}
+
+impl MediaItem {
+    pub fn etag(&self) -> String {
+        format!("\"{:x}-{:x}\"", self.size, self.modified_at.timestamp_millis())
+    }
+}
+
+fn mime_of(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match ext.as_str() {
+        "mp4" | "m4v"   => "video/mp4",
+        "webm"          => "video/webm",
+        "mkv"           => "video/x-matroska",
+        "mov"           => "video/quicktime",
+        "m4a"           => "audio/mp4",
+        "mp3"           => "audio/mpeg",
+        "ogg" | "oga"   => "audio/ogg",
+        "wav"           => "audio/wav",
+        "flac"          => "audio/flac",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+// FNV-1a of the relative path, stable across restarts and rescans
+fn id_of(name: &str) -> String {
+    let hash = name.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+// only mp4 family containers are probed, by reading the `moov` box
+fn probe_duration(path: &Path, mime: &str) -> Option<f64> {
+    if !matches!(mime, "video/mp4" | "audio/mp4" | "video/quicktime") { return None }
+
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut offset = 0u64;
+    // sizes come from the file, a corrupt one must not overflow the walk
+    while offset.checked_add(8).is_some_and(|end| end <= len) {
+        let mut header = [0u8; 16];
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        file.read_exact(&mut header[..8]).ok()?;
+
+        let (size, header_len) = match u32::from_be_bytes(header[0..4].try_into().ok()?) {
+            0 => (len - offset, 8),
+            1 => {
+                file.read_exact(&mut header[8..16]).ok()?;
+                (u64::from_be_bytes(header[8..16].try_into().ok()?), 16)
+            },
+            size => (size as u64, 8),
+        };
+        if size < header_len { return None }
+
+        if &header[4..8] == b"moov" {
+            let payload_len = size - header_len;
+            if payload_len > MAX_MOOV_BYTES { return None }
+            let mut payload = vec![0u8; payload_len as usize];
+            file.read_exact(&mut payload).ok()?;
+            return mp4::duration(&payload);
+        }
+        offset = offset.checked_add(size)?;
+    }
+    None
+}
+
+fn scan_dir(root: &Path, dir: &Path, items: &mut Vec<MediaItem>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = entry.metadata() else { continue };
+        if meta.is_dir() {
+            scan_dir(root, &path, items);
+            continue;
+        }
+        // symlinks are skipped so nothing outside of the root gets served
+        if !meta.is_file() { continue }
+        let Some(mime) = mime_of(&path) else { continue };
+        let Ok(relative) = path.strip_prefix(root) else { continue };
+
+        let name = relative.to_string_lossy().replace('\\', "/");
+        let modified_at = meta.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+        items.push(MediaItem {
+            id: id_of(&name),
+            name,
+            size: meta.len(),
+            mime,
+            duration: probe_duration(&path, mime),
+            modified_at,
+            path,
+        });
+    }
+}
+
+// (re)index every media file under the configured root, returns the number of items
+pub async fn scan(cfg: &MediaConfig) -> usize {
+    let root = PathBuf::from(cfg.root);
+    let items = tokio::task::spawn_blocking(move || {
+        let mut items = vec![];
+        let _ = fs::create_dir_all(&root);
+        scan_dir(&root, &root, &mut items);
+        items
+    }).await;
+    // a failed scan keeps the previous index rather than emptying it
+    let items = match items {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("Media scan failed: {e}");
+            return LIBRARY.len();
+        },
+    };
+
+    LIBRARY.clear();
+    for item in items {
+        LIBRARY.insert(item.id.clone(), item);
+    }
+    LIBRARY.len()
+}
+
+pub fn list() -> Vec<MediaItem> {
+    let mut ret: Vec<MediaItem> = LIBRARY.iter().map(|item| item.value().clone()).collect();
+    ret.sort_by(|a, b| a.name.cmp(&b.name));
+    ret
+}
+
+pub fn get_media(id: &str) -> Result<MediaItem, MediaError> {
+    LIBRARY.get(id).map(|item| item.value().clone()).ok_or(MediaError::MediaNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe_bytes(name: &str, data: &[u8]) -> Option<f64> {
+        let path = std::env::temp_dir().join(format!("strealome-{}-{name}.mp4", std::process::id()));
+        fs::write(&path, data).unwrap();
+        let ret = probe_duration(&path, "video/mp4");
+        let _ = fs::remove_file(&path);
+        ret
+    }
+
+    #[test]
+    fn probe_stops_on_truncated_box() {
+        // declares 4 KiB but the file ends after the header
+        let mut data = 4096u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"ftyp");
+        assert_eq!(probe_bytes("truncated", &data), None);
+    }
+
+    #[test]
+    fn probe_stops_on_oversized_largesize() {
+        // a valid 16 byte box, then a largesize that would overflow the offset
+        let mut data = 16u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"free");
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert_eq!(probe_bytes("oversized", &data), None);
+    }
+
+    #[test]
+    fn probe_stops_on_undersized_box() {
+        let mut data = 4u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"free");
+        assert_eq!(probe_bytes("undersized", &data), None);
+    }
+}
@@ -1,6 +1,15 @@
 pub mod user;
 pub mod chat;
 pub mod room;
+pub mod relay;
+pub mod hls;
+pub mod media;
+pub mod recording;
+pub mod ice;
+pub mod telemetry;
+pub mod thumbnail;
+pub mod subtitle;
+mod mp4;
 mod error;
 
 pub use error::Error;
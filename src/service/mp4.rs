@@ -0,0 +1,53 @@
+// minimal ISO BMFF (mp4) box walking, just enough to read timing information
+
+// iterate over (type, payload) of the boxes laid out in `data`
+pub fn boxes(mut data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    std::iter::from_fn(move || {
+        let size = u32::from_be_bytes(data.get(0..4)?.try_into().ok()?) as usize;
+        let kind = data.get(4..8)?;
+        let (header, size) = match size {
+            0 => (8, data.len()),
+            1 => (16, u64::from_be_bytes(data.get(8..16)?.try_into().ok()?) as usize),
+            _ => (8, size),
+        };
+        let payload = data.get(header..size)?;
+        data = &data[size..];
+        Some((kind, payload))
+    })
+}
+
+pub fn find<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    let (first, rest) = path.split_first()?;
+    let (_, payload) = boxes(data).find(|(kind, _)| kind == first)?;
+    if rest.is_empty() { Some(payload) } else { find(payload, rest) }
+}
+
+// timescale of the first track, from moov/trak/mdia/mdhd
+pub fn timescale(init: &[u8]) -> Option<u32> {
+    let mdhd = find(init, &[b"moov", b"trak", b"mdia", b"mdhd"])?;
+    let offset = if *mdhd.first()? == 1 { 20 } else { 12 };
+    Some(u32::from_be_bytes(mdhd.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+// baseMediaDecodeTime of the first track fragment, from moof/traf/tfdt
+pub fn decode_time(fragment: &[u8]) -> Option<u64> {
+    let tfdt = find(fragment, &[b"moof", b"traf", b"tfdt"])?;
+    if *tfdt.first()? == 1 {
+        Some(u64::from_be_bytes(tfdt.get(4..12)?.try_into().ok()?))
+    } else {
+        Some(u32::from_be_bytes(tfdt.get(4..8)?.try_into().ok()?) as u64)
+    }
+}
+
+// movie duration in seconds, from the payload of a `moov` box
+pub fn duration(moov: &[u8]) -> Option<f64> {
+    let mvhd = find(moov, &[b"mvhd"])?;
+    let (timescale, duration) = if *mvhd.first()? == 1 {
+        (u32::from_be_bytes(mvhd.get(20..24)?.try_into().ok()?),
+         u64::from_be_bytes(mvhd.get(24..32)?.try_into().ok()?))
+    } else {
+        (u32::from_be_bytes(mvhd.get(12..16)?.try_into().ok()?),
+         u32::from_be_bytes(mvhd.get(16..20)?.try_into().ok()?) as u64)
+    };
+    (timescale > 0).then(|| duration as f64 / timescale as f64)
+}
@@ -0,0 +1,209 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use axum::body::Bytes;
+use chrono::Utc;
+use thiserror::Error as ThisError;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::RECORDING_CFG;
+use crate::controller::Response;
+use crate::model::RecordingModel;
+use super::{relay, room, Repository};
+
+// segments waiting for the disk, a writer this far behind ends the recording
+const MAX_QUEUED_SEGMENTS: usize = 1024;
+
+#[derive(Clone, Debug)]
+pub struct RecordingConfig {
+    pub root: &'static str,
+}
+
+#[derive(ThisError, Debug)]
+pub enum RecordingError {
+    #[error("{0}")]
+    RoomError(#[from] room::RoomError),
+
+    #[error("Only the host can manage recordings")]
+    NotHost,
+
+    #[error("Nothing is being streamed")]
+    NotStreaming,
+
+    #[error("Room is already being recorded")]
+    AlreadyRecording,
+
+    #[error("Room is not being recorded")]
+    NotRecording,
+
+    #[error("Recording not found")]
+    RecordingNotFound,
+
+    #[error("Recording is still in progress")]
+    RecordingActive,
+
+    #[error("I/O error")]
+    IOError(#[from] std::io::Error),
+
+    #[error("Service error")]
+    ServiceError(#[from] super::Error),
+}
+
+impl From<RecordingError> for Response {
+    fn from(e: RecordingError) -> Self {
+        Response::error(&e.to_string())
+    }
+}
+
+// handle held by the relay, forwards segments to the writer task
+#[derive(Debug)]
+pub struct Recorder {
+    id:     i32,
+    tx:     mpsc::Sender<Bytes>,
+    synced: bool,
+}
+
+impl Recorder {
+    pub fn write_init(&self, data: &Bytes) -> bool {
+        self.send(data)
+    }
+
+    // returns false once the writer has gone away or fallen behind, the relay then drops
+    // the recorder and the writer finishes what it has; deltas before the first keyframe are skipped
+    pub fn write(&mut self, key: bool, data: &Bytes) -> bool {
+        self.synced |= key;
+        if !self.synced { return true }
+        self.send(data)
+    }
+
+    // never waits, it runs under the relay's stream locks
+    fn send(&self, data: &Bytes) -> bool {
+        match self.tx.try_send(data.clone()) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                eprintln!("Recording {} stopped, the disk cannot keep up", self.id);
+                false
+            },
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+fn extension_of(mime: &str) -> &'static str {
+    if mime.ends_with("webm") || mime.contains("webm;") { "webm" } else { "mp4" }
+}
+
+// drains segments into the recording file until the relay drops its recorder
+async fn write_recording(repo: Arc<dyn Repository>, recording: RecordingModel, mut rx: mpsc::Receiver<Bytes>) {
+    let mut size = 0;
+    match tokio::fs::File::create(&recording.path).await {
+        Ok(mut file) => {
+            while let Some(data) = rx.recv().await {
+                if let Err(e) = file.write_all(&data).await {
+                    eprintln!("Recording {} write error: {e}", recording.id);
+                    break;
+                }
+                size += data.len() as i64;
+            }
+            let _ = file.flush().await;
+        },
+        Err(e) => eprintln!("Recording {} create error: {e}", recording.id),
+    }
+    drop(rx);
+
+    // nothing recorded, leave no trace
+    let res = if size == 0 {
+        let _ = tokio::fs::remove_file(&recording.path).await;
+        repo.delete_recording(recording.id).await.map(|_| ())
+    } else {
+        repo.finish_recording(recording.id, size, Utc::now()).await
+    };
+    if let Err(e) = res {
+        eprintln!("Recording {} finalize error: {e}", recording.id);
+    }
+}
+
+// rows left open by a crash or restart, no recorder can still be writing them at startup
+pub async fn recover(repo: Arc<dyn Repository>) -> Result<usize, RecordingError> {
+    let recordings = repo.find_unfinished_recordings().await.map_err(super::Error::from)?;
+    let cnt = recordings.len();
+    for recording in recordings {
+        let meta = tokio::fs::metadata(&recording.path).await.ok();
+        let res = match meta.filter(|meta| meta.len() > 0) {
+            Some(meta) => {
+                let ended_at = meta.modified().map(Into::into).unwrap_or_else(|_| Utc::now());
+                repo.finish_recording(recording.id, meta.len() as i64, ended_at).await
+            },
+            None => {
+                let _ = tokio::fs::remove_file(&recording.path).await;
+                repo.delete_recording(recording.id).await.map(|_| ())
+            },
+        };
+        res.map_err(super::Error::from)?;
+    }
+    Ok(cnt)
+}
+
+pub async fn start(repo: Arc<dyn Repository>, user_id: i32, room_link: &str) -> Result<RecordingModel, RecordingError> {
+    let room = room::get_room_by_link(room_link)?;
+    if room.host_id() != user_id { return Err(RecordingError::NotHost) }
+    let mime = relay::stream_mime(room_link, user_id).ok_or(RecordingError::NotStreaming)?;
+    if relay::is_recording(room_link, user_id) { return Err(RecordingError::AlreadyRecording) }
+
+    let dir = PathBuf::from(RECORDING_CFG.root).join(room_link);
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(format!("{}.{}", Utc::now().timestamp_millis(), extension_of(&mime)));
+
+    let recording = RecordingModel::new_recording(
+        room_link.to_string(), user_id, mime, path.to_string_lossy().to_string()
+    );
+    let recording = repo.create_recording(recording).await.map_err(super::Error::from)?;
+
+    let (tx, rx) = mpsc::channel(MAX_QUEUED_SEGMENTS);
+    tokio::spawn(write_recording(repo, recording.clone(), rx));
+    if !relay::attach_recorder(room_link, user_id, Recorder { id: recording.id, tx, synced: false }) {
+        return Err(RecordingError::AlreadyRecording);
+    }
+    Ok(recording)
+}
+
+pub fn stop(user_id: i32, room_link: &str) -> Result<(), RecordingError> {
+    let room = room::get_room_by_link(room_link)?;
+    if room.host_id() != user_id { return Err(RecordingError::NotHost) }
+    relay::detach_recorder(room_link, user_id).then_some(()).ok_or(RecordingError::NotRecording)
+}
+
+fn can_access(recording: &RecordingModel, user_id: i32) -> bool {
+    recording.host_id == user_id || room::get_room_by_link(&recording.room_link)
+        .is_ok_and(|room| room.contains_user(user_id).is_ok())
+}
+
+// own recordings, or every accessible recording of one room
+pub async fn list(repo: Arc<dyn Repository>, user_id: i32, room_link: Option<&str>) -> Result<Vec<RecordingModel>, RecordingError> {
+    let recordings = match room_link {
+        Some(room_link) => repo.find_recordings_by_room(room_link).await,
+        None => repo.find_recordings_by_host(user_id).await,
+    }.map_err(super::Error::from)?;
+
+    Ok(recordings.into_iter().filter(|r| can_access(r, user_id)).collect())
+}
+
+pub async fn get_recording(repo: Arc<dyn Repository>, user_id: i32, id: i32) -> Result<RecordingModel, RecordingError> {
+    repo.find_recording(id).await.map_err(super::Error::from)?
+        .filter(|r| can_access(r, user_id))
+        .ok_or(RecordingError::RecordingNotFound)
+}
+
+pub async fn delete(repo: Arc<dyn Repository>, user_id: i32, id: i32) -> Result<(), RecordingError> {
+    let recording = repo.find_recording(id).await.map_err(super::Error::from)?
+        .filter(|r| r.host_id == user_id)
+        .ok_or(RecordingError::RecordingNotFound)?;
+    if recording.ended_at.is_none() { return Err(RecordingError::RecordingActive) }
+
+    if let Err(e) = tokio::fs::remove_file(&recording.path).await
+        && e.kind() != std::io::ErrorKind::NotFound {
+        return Err(e.into());
+    }
+    repo.delete_recording(id).await.map_err(super::Error::from)?;
+    Ok(())
+}
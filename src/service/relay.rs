@@ -0,0 +1,715 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use axum::body::Bytes;
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
+use dashmap::DashMap;
+use futures::{SinkExt, StreamExt};
+use futures::stream::SplitSink;
+use serde::{Deserialize, Serialize};
+use thiserror::Error as ThisError;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::model::{CloseCode, RoomEvent, StreamRole};
+use super::hls::HlsWindow;
+use super::recording::Recorder;
+use super::room::{self, Room};
+
+const VIEWER_BUDGET_BYTES: usize = 8 * 1024 * 1024;
+// a replayed group has to fit a late joiner's budget, or it would be dropped on arrival
+const MAX_GROUP_BYTES: usize = VIEWER_BUDGET_BYTES;
+
+// first byte of every binary frame sent by the publisher
+const SEGMENT_INIT: u8 = 0;
+const SEGMENT_KEY: u8 = 1;
+const SEGMENT_DELTA: u8 = 2;
+
+static STREAMS: LazyLock<DashMap<(String, i32), Arc<MediaStream>>> = LazyLock::new(DashMap::new); // (link, track) -> stream
+
+#[derive(ThisError, Debug)]
+pub enum RelayError {
+    #[error("{0}")]
+    RoomError(#[from] room::RoomError),
+
+    #[error("Socket error")]
+    SocketError(#[from] axum::Error),
+
+    #[error("Stream role does not match the room")]
+    RoleMismatch,
+
+    #[error("Track is already being published")]
+    AlreadyPublishing,
+
+    #[error("Track not found")]
+    TrackNotFound,
+
+}
+
+// publisher -> server
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "content")]
+enum PublishCommand {
+    #[serde(rename = "start")]
+    Start {
+        mime: String,
+    },
+    #[serde(rename = "stop")]
+    Stop,
+}
+
+// server -> client, the track is the publisher's user id
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "content")]
+enum MediaEvent {
+    #[serde(rename = "start")]
+    Start {
+        track: i32,
+        mime: String,
+    },
+    #[serde(rename = "stop")]
+    Stop {
+        track: i32,
+    },
+    #[serde(rename = "closed")]
+    Closed {
+        reason: String,
+    },
+    #[serde(rename = "error")]
+    Error {
+        reason: String,
+    },
+}
+
+impl MediaEvent {
+    fn error(reason: &str) -> Self {
+        MediaEvent::Error { reason: reason.to_string() }
+    }
+}
+
+impl From<&MediaEvent> for Message {
+    fn from(event: &MediaEvent) -> Self {
+        Message::Text(serde_json::to_string(event).unwrap_or_default().into())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum MediaPacket {
+    Event(Arc<MediaEvent>),
+    Init(Bytes),
+    Chunk { key: bool, data: Bytes },
+    Close { code: CloseCode, reason: String }, // last packet of a kicked viewer
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    packets:    VecDeque<MediaPacket>,
+    bytes:      usize, // chunk bytes only, events and init segments are not budgeted
+    skipping:   bool, // waiting for the next keyframe after an overflow
+    closed:     bool,
+}
+
+// per-viewer send queue, a slow viewer loses whole groups instead of holding memory
+#[derive(Debug)]
+struct ViewerQueue {
+    user_id:        i32,
+    state:          Mutex<QueueState>,
+    notify:         Notify,
+    dropped_groups: AtomicU64,
+    dropped_bytes:  AtomicU64,
+}
+
+impl ViewerQueue {
+    fn new(user_id: i32) -> Self {
+        Self {
+            user_id,
+            state: Mutex::new(QueueState::default()),
+            notify: Notify::new(),
+            dropped_groups: AtomicU64::new(0),
+            dropped_bytes: AtomicU64::new(0),
+        }
+    }
+
+    // over budget, queued chunks are discarded and delivery resumes at the next keyframe
+    fn push(&self, packet: MediaPacket) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed { return }
+
+        if let MediaPacket::Chunk { key, data } = &packet {
+            state.skipping &= !key;
+            if state.skipping {
+                self.dropped_bytes.fetch_add(data.len() as u64, Ordering::Relaxed);
+                return;
+            }
+            if state.bytes + data.len() > VIEWER_BUDGET_BYTES {
+                state.packets.retain(|p| !matches!(p, MediaPacket::Chunk { .. }));
+                self.dropped_groups.fetch_add(1, Ordering::Relaxed);
+                self.dropped_bytes.fetch_add(state.bytes as u64, Ordering::Relaxed);
+                state.bytes = 0;
+                if !key {
+                    self.dropped_bytes.fetch_add(data.len() as u64, Ordering::Relaxed);
+                    state.skipping = true;
+                    return;
+                }
+            }
+            state.bytes += data.len();
+        }
+
+        state.packets.push_back(packet);
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    async fn pop(&self) -> Option<MediaPacket> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(packet) = state.packets.pop_front() {
+                    if let MediaPacket::Chunk { data, .. } = &packet {
+                        state.bytes -= data.len();
+                    }
+                    return Some(packet);
+                }
+                if state.closed { return None }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.packets.clear();
+        state.bytes = 0;
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    // like `close`, but the viewer gets a close frame first
+    fn kick(&self, code: CloseCode, reason: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed { return }
+        state.closed = true;
+        state.packets.clear();
+        state.packets.push_back(MediaPacket::Close { code, reason: reason.to_string() });
+        state.bytes = 0;
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewerStats {
+    pub user_id:        i32,
+    pub track:          i32,
+    pub queued_bytes:   usize,
+    pub dropped_groups: u64,
+    pub dropped_bytes:  u64,
+}
+
+#[derive(Debug, Default)]
+struct StreamCache {
+    mime:           Option<String>,
+    init:           Option<Bytes>,
+    group:          Vec<Bytes>, // most recent keyframe group
+    group_bytes:    usize,
+}
+
+impl StreamCache {
+    fn clear_group(&mut self) {
+        self.group.clear();
+        self.group_bytes = 0;
+    }
+}
+
+#[derive(Debug)]
+pub struct MediaStream {
+    track:      i32,
+    cache:      Mutex<StreamCache>,
+    hls:        Mutex<HlsWindow>, // always locked after `cache`
+    recorder:   Mutex<Option<Recorder>>, // always locked after `cache`
+    viewers:    Mutex<Vec<Arc<ViewerQueue>>>, // always locked after `cache`
+    publishing: AtomicBool,
+}
+
+impl MediaStream {
+    fn new(track: i32) -> Self {
+        Self {
+            track,
+            cache: Mutex::new(StreamCache::default()),
+            hls: Mutex::new(HlsWindow::default()),
+            recorder: Mutex::new(None),
+            viewers: Mutex::new(vec![]),
+            publishing: AtomicBool::new(false),
+        }
+    }
+
+    // fan out without ever waiting on a viewer, closed queues are pruned on the way
+    fn send(&self, packet: MediaPacket) {
+        let mut viewers = self.viewers.lock().unwrap();
+        viewers.retain(|viewer| !viewer.is_closed());
+        for viewer in viewers.iter() {
+            viewer.push(packet.clone());
+        }
+    }
+
+    // packets are sent while holding the cache lock, so that a subscriber
+    // never misses or duplicates a packet between its backlog and live feed
+    fn start(&self, mime: String) -> Result<(), &'static str> {
+        const SUPPORTED: [&str; 4] = ["video/mp4", "video/webm", "audio/mp4", "audio/webm"];
+        if !SUPPORTED.iter().any(|m| mime.starts_with(m)) {
+            return Err("Unsupported media type");
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        *cache = StreamCache { mime: Some(mime.clone()), ..Default::default() };
+        self.hls.lock().unwrap().reset(&mime);
+        self.recorder.lock().unwrap().take();
+        self.send(MediaPacket::Event(Arc::new(MediaEvent::Start { track: self.track, mime })));
+        Ok(())
+    }
+
+    fn stop(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.mime.is_none() { return }
+        *cache = StreamCache::default();
+        self.hls.lock().unwrap().finish();
+        self.recorder.lock().unwrap().take();
+        self.send(MediaPacket::Event(Arc::new(MediaEvent::Stop { track: self.track })));
+    }
+
+    fn push(&self, kind: u8, data: Bytes) -> Result<(), &'static str> {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.mime.is_none() { return Err("Stream not started") }
+
+        let packet = match kind {
+            SEGMENT_INIT => {
+                if !is_init_segment(&data) { return Err("Unrecognized init segment") }
+                cache.init = Some(data.clone());
+                cache.clear_group();
+                self.hls.lock().unwrap().push_init(&data);
+                // a recording holds a single init segment, a new one ends it
+                self.recorder.lock().unwrap().take();
+                MediaPacket::Init(data)
+            },
+            SEGMENT_KEY | SEGMENT_DELTA => {
+                if cache.init.is_none() { return Err("Init segment required first") }
+                let key = kind == SEGMENT_KEY;
+                if key { cache.clear_group() }
+                if !cache.group.is_empty() || key {
+                    if cache.group_bytes + data.len() > MAX_GROUP_BYTES {
+                        // too large to replay, late joiners wait for the next keyframe
+                        cache.clear_group();
+                    } else {
+                        cache.group_bytes += data.len();
+                        cache.group.push(data.clone());
+                    }
+                }
+                self.hls.lock().unwrap().push_chunk(key, &data);
+                let mut recorder = self.recorder.lock().unwrap();
+                if recorder.as_mut().is_some_and(|r| !r.write(key, &data)) {
+                    recorder.take();
+                }
+                drop(recorder);
+                MediaPacket::Chunk { key, data }
+            },
+            _ => return Err("Unknown segment kind"),
+        };
+
+        self.send(packet);
+        Ok(())
+    }
+
+    fn subscribe(&self, user_id: i32) -> Arc<ViewerQueue> {
+        let cache = self.cache.lock().unwrap();
+
+        let mut backlog = vec![];
+        if let Some(mime) = &cache.mime {
+            backlog.push(MediaPacket::Event(Arc::new(MediaEvent::Start { track: self.track, mime: mime.clone() })));
+        }
+        if let Some(init) = &cache.init {
+            backlog.push(MediaPacket::Init(init.clone()));
+        }
+        backlog.extend(cache.group.iter().enumerate()
+            .map(|(i, data)| MediaPacket::Chunk { key: i == 0, data: data.clone() }));
+
+        let queue = Arc::new(ViewerQueue::new(user_id));
+        for packet in backlog {
+            queue.push(packet);
+        }
+        self.viewers.lock().unwrap().push(queue.clone());
+        queue
+    }
+
+    fn kick(&self, user_id: i32, code: CloseCode, reason: &str) {
+        let viewers = self.viewers.lock().unwrap();
+        for viewer in viewers.iter().filter(|viewer| viewer.user_id == user_id) {
+            viewer.kick(code, reason);
+        }
+    }
+
+    fn viewer_stats(&self) -> Vec<ViewerStats> {
+        let viewers = self.viewers.lock().unwrap();
+        viewers.iter()
+            .filter(|viewer| !viewer.is_closed())
+            .map(|viewer| ViewerStats {
+                user_id: viewer.user_id,
+                track: self.track,
+                queued_bytes: viewer.state.lock().unwrap().bytes,
+                dropped_groups: viewer.dropped_groups.load(Ordering::Relaxed),
+                dropped_bytes: viewer.dropped_bytes.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+// viewers of a released stream are disconnected
+impl Drop for MediaStream {
+    fn drop(&mut self) {
+        for viewer in self.viewers.get_mut().unwrap().iter() {
+            viewer.close();
+        }
+    }
+}
+
+// fMP4 starts with an `ftyp` box, WebM with the EBML magic
+fn is_init_segment(data: &[u8]) -> bool {
+    const EBML_MAGIC: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+    data.starts_with(&EBML_MAGIC) || data.get(4..8) == Some(b"ftyp")
+}
+
+fn stream_key(room_link: &str, track: i32) -> (String, i32) {
+    (room_link.to_string(), track)
+}
+
+fn stream_of(room_link: &str, track: i32) -> Arc<MediaStream> {
+    STREAMS.entry(stream_key(room_link, track))
+        .or_insert_with(|| Arc::new(MediaStream::new(track)))
+        .clone()
+}
+
+fn find_stream(room_link: &str, track: i32) -> Option<Arc<MediaStream>> {
+    STREAMS.get(&stream_key(room_link, track)).map(|s| s.clone())
+}
+
+pub fn with_hls<T>(room_link: &str, track: i32, f: impl FnOnce(&HlsWindow) -> T) -> Option<T> {
+    let stream = find_stream(room_link, track)?;
+    let hls = stream.hls.lock().unwrap();
+    Some(f(&hls))
+}
+
+// mime of a started track, whether or not it has received its init segment
+pub fn track_mime(room_link: &str, track: i32) -> Option<String> {
+    let stream = find_stream(room_link, track)?;
+    let cache = stream.cache.lock().unwrap();
+    cache.mime.clone()
+}
+
+// mime of a track that has received its init segment
+pub fn stream_mime(room_link: &str, track: i32) -> Option<String> {
+    let stream = find_stream(room_link, track)?;
+    let cache = stream.cache.lock().unwrap();
+    cache.init.as_ref().and(cache.mime.clone())
+}
+
+pub fn is_recording(room_link: &str, track: i32) -> bool {
+    find_stream(room_link, track)
+        .is_some_and(|stream| stream.recorder.lock().unwrap().is_some())
+}
+
+// starts the recorder with the current init segment, fails if there is none or already a recorder
+pub fn attach_recorder(room_link: &str, track: i32, recorder: Recorder) -> bool {
+    let Some(stream) = find_stream(room_link, track) else { return false };
+    let cache = stream.cache.lock().unwrap();
+    let Some(init) = &cache.init else { return false };
+
+    let mut slot = stream.recorder.lock().unwrap();
+    if slot.is_some() || !recorder.write_init(init) { return false }
+    *slot = Some(recorder);
+    true
+}
+
+// dropping the recorder lets the writer finish the file
+pub fn detach_recorder(room_link: &str, track: i32) -> bool {
+    let Some(stream) = find_stream(room_link, track) else { return false };
+    let _cache = stream.cache.lock().unwrap();
+    stream.recorder.lock().unwrap().take().is_some()
+}
+
+// stop and forget one publisher's track, its viewers get a `stop`
+pub fn release_track(room_link: &str, track: i32) {
+    if let Some((_, stream)) = STREAMS.remove(&stream_key(room_link, track)) {
+        stream.stop();
+    }
+}
+
+// drop every cached track and HLS window of a released room
+pub fn release(room_link: &str) {
+    let mut released = vec![];
+    STREAMS.retain(|(link, _), stream| {
+        if link != room_link { return true }
+        released.push(stream.clone());
+        false
+    });
+    for stream in released {
+        stream.stop();
+    }
+}
+
+// disconnect a kicked or banned user from every track they watch
+pub fn expel(room_link: &str, user_id: i32, code: CloseCode, reason: &str) {
+    let streams: Vec<Arc<MediaStream>> = STREAMS.iter()
+        .filter(|item| item.key().0 == room_link)
+        .map(|item| item.value().clone())
+        .collect();
+    for stream in streams {
+        stream.kick(user_id, code, reason);
+    }
+}
+
+// relay counters of every viewer of every track in the room
+pub fn viewer_stats(room_link: &str) -> Vec<ViewerStats> {
+    let streams: Vec<Arc<MediaStream>> = STREAMS.iter()
+        .filter(|item| item.key().0 == room_link)
+        .map(|item| item.value().clone())
+        .collect();
+    streams.iter().flat_map(|stream| stream.viewer_stats()).collect()
+}
+
+async fn announce_tracks(room: &Room) {
+    room.broadcast(&RoomEvent::Tracks(room.tracks())).await;
+}
+
+async fn handle_publisher(mut socket: WebSocket, room: Room, user_id: i32) -> Result<(), RelayError> {
+    let stream = stream_of(&room.share_link(), user_id);
+    if stream.publishing.swap(true, Ordering::AcqRel) {
+        return Err(RelayError::AlreadyPublishing);
+    }
+
+    let mut res = Ok(());
+    loop {
+        let msg = tokio::select! {
+            msg = socket.recv() => msg,
+            reason = room.closed() => {
+                let frame = CloseFrame { code: CloseCode::RoomClosed as u16, reason: reason.into() };
+                res = socket.send(Message::Close(Some(frame))).await;
+                break;
+            }
+        };
+        let Some(Ok(msg)) = msg else { break };
+        room.touch();
+
+        // publish rights may be revoked mid-stream
+        if !room.can_publish(user_id) {
+            res = socket.send((&MediaEvent::error("Publish rights revoked")).into()).await;
+            break;
+        }
+
+        let ret = match msg {
+            Message::Text(text) => match serde_json::from_str::<PublishCommand>(&text) {
+                Ok(PublishCommand::Start { mime }) => {
+                    let ret = stream.start(mime);
+                    if ret.is_ok() { announce_tracks(&room).await }
+                    ret
+                },
+                Ok(PublishCommand::Stop) => {
+                    stream.stop();
+                    announce_tracks(&room).await;
+                    Ok(())
+                },
+                _ => Err("Bad media message"),
+            },
+            Message::Binary(data) => match data.first() {
+                Some(&kind) => stream.push(kind, data.slice(1..)),
+                None => Err("Empty segment"),
+            },
+            _ => Ok(()),
+        };
+
+        if let Err(reason) = ret {
+            res = socket.send((&MediaEvent::error(reason)).into()).await;
+            if res.is_err() { break }
+        }
+    }
+
+    stream.stop();
+    stream.publishing.store(false, Ordering::Release);
+    announce_tracks(&room).await;
+    Ok(res?)
+}
+
+// skips delta chunks until the viewer has seen a keyframe after the latest init
+async fn forward(
+    sender: &mut SplitSink<WebSocket, Message>,
+    packet: MediaPacket, synced: &mut bool
+) -> Result<(), axum::Error> {
+    let msg = match packet {
+        MediaPacket::Event(event) => {
+            *synced = false;
+            event.as_ref().into()
+        },
+        MediaPacket::Init(data) => {
+            *synced = false;
+            Message::Binary(data)
+        },
+        MediaPacket::Chunk { key, data } => {
+            *synced |= key;
+            if !*synced { return Ok(()) }
+            Message::Binary(data)
+        },
+        MediaPacket::Close { code, reason } => {
+            Message::Close(Some(CloseFrame { code: code as u16, reason: reason.into() }))
+        },
+    };
+    sender.send(msg).await
+}
+
+async fn handle_viewer(socket: WebSocket, room: Room, stream: Arc<MediaStream>, user_id: i32) -> Result<(), RelayError> {
+    let queue = stream.subscribe(user_id);
+    drop(stream);
+    let (mut sender, mut recver) = socket.split();
+
+    let _queue = queue.clone();
+    let send_fut = async move {
+        let mut synced = false;
+        loop {
+            let packet = tokio::select! {
+                biased;
+                reason = room.closed() => {
+                    sender.send((&MediaEvent::Closed { reason: reason.clone() }).into()).await?;
+                    let frame = CloseFrame { code: CloseCode::RoomClosed as u16, reason: reason.into() };
+                    sender.send(Message::Close(Some(frame))).await?;
+                    break;
+                }
+                packet = _queue.pop() => packet,
+            };
+            let Some(packet) = packet else { break };
+            forward(&mut sender, packet, &mut synced).await?;
+        }
+        Ok(())
+    };
+
+    // viewers only listen, drain until the socket closes
+    let recv_fut = async move {
+        while let Some(Ok(_)) = recver.next().await {}
+        Ok(())
+    };
+
+    let mut send_task: JoinHandle<Result<(), RelayError>> = tokio::spawn(send_fut);
+    let mut recv_task: JoinHandle<Result<(), RelayError>> = tokio::spawn(recv_fut);
+
+    let res = tokio::select! {
+        res = &mut send_task => {
+            recv_task.abort();
+            res.unwrap_or(Ok(()))
+        }
+        _ = &mut recv_task => {
+            send_task.abort();
+            Ok(())
+        }
+    };
+    queue.close();
+    res
+}
+
+// publishers feed their own track unless they ask for someone else's,
+// viewers pick one, the host's by default
+pub async fn handle_websocket(
+    socket: WebSocket, room_link: String,
+    user_id: i32, role: StreamRole, track: Option<i32>
+) -> Result<(), RelayError> {
+    let room = room::get_room_by_link(&room_link)?;
+    if role.is_publisher() != room.stream_role(user_id)?.is_publisher() {
+        return Err(RelayError::RoleMismatch);
+    }
+
+    if role.is_publisher() && track.is_none_or(|track| track == user_id) {
+        return handle_publisher(socket, room, user_id).await;
+    }
+    let track = track.unwrap_or(room.host_id());
+    if !room.can_publish(track) { return Err(RelayError::TrackNotFound) }
+    let stream = stream_of(&room_link, track);
+    handle_viewer(socket, room, stream, user_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: usize = 1024 * 1024;
+
+    fn init_segment() -> Bytes {
+        Bytes::from_static(b"\0\0\0\x10ftypisom\0\0\0\0")
+    }
+
+    fn chunk(len: usize) -> Bytes {
+        Bytes::from(vec![0u8; len])
+    }
+
+    fn drain(queue: &ViewerQueue) -> Vec<MediaPacket> {
+        let mut state = queue.state.lock().unwrap();
+        state.bytes = 0;
+        state.packets.drain(..).collect()
+    }
+
+    #[test]
+    fn late_joiner_never_starts_without_a_keyframe() {
+        let stream = MediaStream::new(1);
+        stream.start("video/mp4".to_string()).unwrap();
+        stream.push(SEGMENT_INIT, init_segment()).unwrap();
+        stream.push(SEGMENT_KEY, chunk(5 * MB)).unwrap();
+        stream.push(SEGMENT_DELTA, chunk(5 * MB)).unwrap();
+        stream.push(SEGMENT_DELTA, chunk(MB)).unwrap();
+
+        let queue = stream.subscribe(2);
+        let packets = drain(&queue);
+        assert_eq!(queue.dropped_groups.load(Ordering::Relaxed), 0);
+        assert!(matches!(packets[..], [MediaPacket::Event(_), MediaPacket::Init(_)]));
+
+        // the next keyframe starts a group that is replayed in full
+        stream.push(SEGMENT_KEY, chunk(3 * MB)).unwrap();
+        stream.push(SEGMENT_DELTA, chunk(3 * MB)).unwrap();
+        let late = stream.subscribe(3);
+        let chunks: Vec<bool> = drain(&late).into_iter()
+            .filter_map(|p| match p { MediaPacket::Chunk { key, .. } => Some(key), _ => None })
+            .collect();
+        assert_eq!(chunks, [true, false]);
+        assert_eq!(late.dropped_groups.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn slow_viewer_loses_whole_groups() {
+        let queue = ViewerQueue::new(2);
+        queue.push(MediaPacket::Chunk { key: true, data: chunk(5 * MB) });
+        queue.push(MediaPacket::Event(Arc::new(MediaEvent::Stop { track: 1 })));
+        queue.push(MediaPacket::Chunk { key: false, data: chunk(2 * MB) });
+        // over budget: the queued group goes, and so does the rest of it
+        queue.push(MediaPacket::Chunk { key: false, data: chunk(2 * MB) });
+        queue.push(MediaPacket::Chunk { key: false, data: chunk(MB) });
+        assert_eq!(queue.state.lock().unwrap().bytes, 0);
+        queue.push(MediaPacket::Chunk { key: true, data: chunk(MB) });
+
+        assert_eq!(queue.dropped_groups.load(Ordering::Relaxed), 1);
+        assert_eq!(queue.dropped_bytes.load(Ordering::Relaxed), 10 * MB as u64);
+        let packets = drain(&queue);
+        assert!(matches!(packets[..], [MediaPacket::Event(_), MediaPacket::Chunk { key: true, .. }]));
+    }
+
+    #[tokio::test]
+    async fn kicked_viewer_gets_only_the_close() {
+        let queue = ViewerQueue::new(2);
+        queue.push(MediaPacket::Init(init_segment()));
+        queue.push(MediaPacket::Chunk { key: true, data: chunk(MB) });
+        queue.kick(CloseCode::Kicked, "bye");
+        queue.kick(CloseCode::Banned, "again");
+        queue.push(MediaPacket::Chunk { key: false, data: chunk(MB) });
+
+        assert!(queue.is_closed());
+        assert!(matches!(queue.pop().await, Some(MediaPacket::Close { code: CloseCode::Kicked, .. })));
+        assert!(queue.pop().await.is_none());
+    }
+}
@@ -1,16 +1,38 @@
 use std::cell::RefCell;
-use std::sync::{Arc, LazyLock, RwLock};
-use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicI64, AtomicU64, Ordering};
 use chrono::{DateTime, Timelike, Utc};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
 use rand::RngCore;
-use tokio::sync::{mpsc};
-use dashmap::{DashMap};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use dashmap::{DashMap, DashSet};
+use dashmap::mapref::entry::Entry;
 
-use crate::model::{ChatMessage, ChatMessageContent};
+use crate::ROOM_CFG;
+use crate::model::{ChatMessage, ChatMessageContent, RoomMember, StatsReport, StreamHealth, StreamRole, StreamTrack, SubtitleTrack};
+use crate::model::{CloseCode, ModerationAction, Permission, PlaybackCommand, PlaybackState, RoomAccess, RoomRole, RoomEvent, RoomMessage, RoomModel};
+use crate::repository::Repository;
+use crate::signal;
+use super::media::{self, MediaItem};
+use super::relay;
+use super::telemetry::Telemetry;
+use super::subtitle;
+use super::thumbnail::Thumbnail;
+use super::user;
 
 const ROOM_SHARE_LINK_LEN: usize = 8;
-const ROOM_RELEASE_DURATION_S: i64 = 15;
+const MAX_SUBTITLE_TRACKS: usize = 32; // per media item
+const MAX_INVITED: usize = 256;
+const MAX_LIST_LIMIT: usize = 100;
+const MAX_REASON_LEN: usize = 200;
+const MAX_NAME_LEN: usize = 64;
 static ROOMS: LazyLock<Rooms> = LazyLock::new(|| Rooms::new());
+static REPO: OnceLock<Arc<dyn Repository>> = OnceLock::new(); // rooms are written through once loaded
+static CONN_ID: AtomicU64 = AtomicU64::new(1);
 thread_local! {
     static RNG: RefCell<rand::rngs::ThreadRng> = RefCell::new(rand::thread_rng());
 }
@@ -18,6 +40,18 @@ thread_local! {
 use thiserror::Error as ThisError;
 use crate::controller::Response;
 
+#[derive(Clone, Debug)]
+pub struct RoomConfig {
+    pub idle_timeout:   i64, // seconds without activity before a room is unloaded
+    pub reap_interval:  u64, // seconds between reaper runs
+    pub tombstone_ttl:  i64, // seconds a deleted link keeps answering `RoomReleased`, reset by a restart
+    pub max_hosted_rooms: usize, // per user, at the same time
+    pub max_members:    usize, // ceiling for every room's capacity
+    pub admins:         &'static [i32], // user_ids exempt from both limits
+    pub heartbeat_interval: u64, // seconds between server pings on chat sockets
+    pub heartbeat_timeout:  u64, // seconds of silence before a chat socket is dropped
+}
+
 #[derive(Debug, ThisError)]
 pub enum RoomError {
     #[error("Room not found")]
@@ -26,6 +60,24 @@ pub enum RoomError {
     RoomReleased,
     #[error("User not in room")]
     UserNotFound,
+    #[error("Forbidden: missing {0} permission")]
+    Forbidden(Permission),
+    #[error("This room is invite-only")]
+    NotInvited,
+    #[error("Incorrect room password")]
+    IncorrectPassword,
+    #[error("Join the room first")]
+    NotJoined,
+    #[error("You are banned from this room")]
+    Banned,
+    #[error("You are muted")]
+    Muted,
+    #[error("Room is full")]
+    RoomFull,
+    #[error("You can host at most {0} rooms at a time")]
+    QuotaExceeded(usize),
+    #[error("{0}")]
+    InvalidArgument(&'static str),
     #[error("Internal Error")]
     InternalError,
 }
@@ -36,34 +88,484 @@ impl From<RoomError> for Response {
     }
 }
 
+pub struct RoomAccessParam {
+    pub access:     RoomAccess,
+    pub password:   Option<String>, // plain, required for `Password`
+    pub invited:    Vec<i32>, // allowlist for `Invite`
+    pub listed:     bool, // shown by `/room/list`
+    pub capacity:   Option<u32>, // the server-wide ceiling when unset
+}
+
+// fields left `None` are kept, a password room keeps its password unless a new one is given
+#[derive(Default)]
+pub struct RoomUpdateParam {
+    pub name:       Option<String>,
+    pub access:     Option<RoomAccess>,
+    pub password:   Option<String>,
+    pub invited:    Option<Vec<i32>>,
+    pub listed:     Option<bool>,
+    pub capacity:   Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomStatus {
+    #[default]
+    All,
+    Live, // someone is connected
+    Empty,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomSort {
+    #[default]
+    Created, // newest first
+    Members, // fullest first
+}
+
+pub type ConnId = u64;
+// why the room hung up on a chat socket, watched by its handler
+pub type Hangup = watch::Receiver<Option<(CloseCode, String)>>;
+
+// one chat socket
+#[derive(Clone, Debug)]
+struct Conn {
+    id:         ConnId,
+    tx:         mpsc::Sender<Arc<RoomMessage>>,
+    hangup:     watch::Sender<Option<(CloseCode, String)>>, // beside the queue, a full one cannot lose it
+}
+
+impl Conn {
+    // the first reason wins
+    fn hang_up(&self, code: CloseCode, reason: &str) {
+        self.hangup.send_if_modified(|hangup| {
+            if hangup.is_some() { return false }
+            *hangup = Some((code, reason.to_string()));
+            true
+        });
+    }
+}
+
+// a connected user with one chat socket per tab or device
+#[derive(Clone, Debug)]
+struct Member {
+    name:       String,
+    joined_at:  DateTime<Utc>, // of the first connection
+    last_seen:  DateTime<Utc>, // any frame from any connection, pongs included
+    conns:      Vec<Conn>,
+}
+
+pub struct ListParam {
+    pub query:  String, // name or host substring, case insensitive
+    pub status: RoomStatus,
+    pub sort:   RoomSort,
+    pub cursor: Option<String>, // `next_cursor` of the previous page
+    pub limit:  usize,
+}
+
 #[derive(Clone, Debug)]
 pub struct Room {
     link:       Arc<String>, // pk
 
-    host_id:    i32,
+    host_id:    Arc<AtomicI32>, // changes hands through `Rooms::transfer`
     host_name:  Arc<RwLock<String>>,
     name:       Arc<RwLock<String>>,
-    users:      Arc<DashMap<i32, mpsc::Sender<Arc<ChatMessage>>>>, // user_id -> user_name
+    users:      Arc<DashMap<i32, Member>>, // user_id -> connected member
+    seats:      Arc<Mutex<()>>, // held from the capacity check until a new member is in `users`
     created_at: DateTime<Utc>,
+
+    access:     Arc<RwLock<RoomAccess>>,
+    password:   Arc<RwLock<Option<String>>>, // bcrypt hash
+    invited:    Arc<DashSet<i32>>, // user_ids allowed into an invite-only room
+    members:    Arc<DashSet<i32>>, // user_ids admitted through `join`, chat sockets are limited to these
+    listed:     Arc<AtomicBool>,
+    banned:     Arc<DashSet<i32>>, // persisted, checked by `admit`
+    muted:      Arc<DashSet<i32>>, // may watch but not chat
+    roles:      Arc<DashMap<i32, RoomRole>>, // assigned by the host, everyone else is a member
+    capacity:   Arc<RwLock<Option<u32>>>,
+    
+    playback:           Arc<RwLock<PlaybackState>>,
+    playback_delegates: Arc<DashSet<i32>>, // user_ids allowed to control playback besides the host
+    publishers:         Arc<DashSet<i32>>, // user_ids allowed to publish besides the host
+    telemetry:          Arc<Telemetry>,
+    thumbnail:          Arc<RwLock<Option<Arc<Thumbnail>>>>, // goes away with the room
+    subtitles:          Arc<DashMap<u32, Arc<SubtitleTrack>>>, // track id -> track
+    
+    last_active:        Arc<AtomicI64>, // unix seconds
+    closed:             Arc<watch::Sender<Option<String>>>, // close reason, set once
 }
 
 impl Room {
-    fn new(host_id: i32, host_name: String, name: String, link: String) -> Self {
-        let created_at = Utc::now();
+    fn new(model: RoomModel, host_name: String) -> Self {
         Self {
-            host_id,
+            host_id: Arc::new(AtomicI32::new(model.host_id)),
             host_name: Arc::new(RwLock::new(host_name)),
-            link: Arc::new(link.clone()),
-            name: Arc::new(RwLock::new(name)),
+            link: Arc::new(model.link),
+            name: Arc::new(RwLock::new(model.name)),
             users: Arc::new(DashMap::new()),
-            created_at,
+            seats: Arc::new(Mutex::new(())),
+            created_at: model.created_at,
+            access: Arc::new(RwLock::new(model.access)),
+            password: Arc::new(RwLock::new(model.password)),
+            invited: Arc::new(model.invited.into_iter().collect()),
+            members: Arc::new(DashSet::new()),
+            listed: Arc::new(AtomicBool::new(model.listed)),
+            banned: Arc::new(model.banned.into_iter().collect()),
+            muted: Arc::new(DashSet::new()),
+            capacity: Arc::new(RwLock::new(model.capacity)),
+            roles: Arc::new(model.roles.into_iter().filter(|(id, _)| *id != model.host_id).collect()),
+            playback: Arc::new(RwLock::new(PlaybackState::default())),
+            playback_delegates: Arc::new(DashSet::new()),
+            publishers: Arc::new(DashSet::new()),
+            telemetry: Arc::new(Telemetry::default()),
+            thumbnail: Arc::new(RwLock::new(None)),
+            subtitles: Arc::new(DashMap::new()),
+            last_active: Arc::new(AtomicI64::new(Utc::now().timestamp())),
+            closed: Arc::new(watch::Sender::new(None)),
+        }
+    }
+    
+    // keeps the room away from the reaper
+    pub fn touch(&self) {
+        self.last_active.store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+    
+    // a frame arrived from the user, which also keeps the room alive
+    pub fn seen(&self, user_id: i32) {
+        if let Some(mut member) = self.users.get_mut(&user_id) {
+            member.last_seen = Utc::now();
+        }
+        self.touch();
+    }
+    
+    fn idle_since(&self) -> i64 {
+        self.last_active.load(Ordering::Relaxed)
+    }
+    
+    pub fn is_closed(&self) -> bool {
+        self.closed.borrow().is_some()
+    }
+    
+    // resolves with the reason once the room has been closed
+    pub async fn closed(&self) -> String {
+        let mut rx = self.closed.subscribe();
+        rx.wait_for(|reason| reason.is_some()).await
+            .map(|reason| reason.clone().unwrap_or_default())
+            .unwrap_or_default()
+    }
+    
+    // tell every member why, then end their sockets, other sockets watch `closed`
+    async fn close(&self, reason: &str) {
+        let first = self.closed.send_if_modified(|closed| {
+            if closed.is_some() { return false }
+            *closed = Some(reason.to_string());
+            true
+        });
+        if !first { return }
+        
+        self.broadcast(&RoomEvent::Closed { reason: reason.to_string() }).await;
+        let conns = self.connections(|_, _| true);
+        self.users.clear();
+        conns.iter().for_each(|conn| conn.hang_up(CloseCode::RoomClosed, reason));
+        self.thumbnail.write().unwrap().take();
+    }
+
+    pub fn access(&self) -> RoomAccess {
+        *self.access.read().unwrap()
+    }
+
+    pub fn is_listed(&self) -> bool {
+        self.listed.load(Ordering::Relaxed)
+    }
+
+    // connected members allowed at once, a lowered ceiling applies to existing rooms as well
+    pub fn capacity(&self) -> usize {
+        self.capacity.read().unwrap().map_or(ROOM_CFG.max_members, |c| c as usize).min(ROOM_CFG.max_members)
+    }
+
+    // the host, admins and already connected members always get in
+    pub fn check_capacity(&self, user_id: i32) -> Result<(), RoomError> {
+        if user_id == self.host_id() || is_admin(user_id) || self.users.contains_key(&user_id) { return Ok(()) }
+        (self.users.len() < self.capacity()).then_some(()).ok_or(RoomError::RoomFull)
+    }
+
+    pub fn is_member(&self, user_id: i32) -> bool {
+        user_id == self.host_id() || self.members.contains(&user_id)
+    }
+
+    // checks the access mode once, admitted users may reconnect freely
+    pub fn admit(&self, user_id: i32, password: Option<&str>) -> Result<(), RoomError> {
+        if self.is_closed() { return Err(RoomError::RoomReleased) }
+        if self.banned.contains(&user_id) { return Err(RoomError::Banned) }
+        self.check_capacity(user_id)?;
+        if self.is_member(user_id) { return Ok(()) }
+        match self.access() {
+            RoomAccess::Open => {},
+            RoomAccess::Password => {
+                let hash = self.password.read().unwrap().clone().ok_or(RoomError::InternalError)?;
+                if !password.is_some_and(|p| user::verify_password(p, &hash)) {
+                    return Err(RoomError::IncorrectPassword);
+                }
+            },
+            RoomAccess::Invite => {
+                if !self.invited.contains(&user_id) { return Err(RoomError::NotInvited) }
+            },
+        }
+        self.members.insert(user_id);
+        self.touch();
+        Ok(())
+    }
+
+    fn model(&self) -> RoomModel {
+        RoomModel {
+            link: self.share_link(),
+            host_id: self.host_id(),
+            name: self.name(),
+            created_at: self.created_at,
+            access: self.access(),
+            password: self.password.read().unwrap().clone(),
+            invited: self.invited.iter().map(|id| *id).collect(),
+            listed: self.is_listed(),
+            banned: self.banned.iter().map(|id| *id).collect(),
+            roles: self.roles.iter().map(|item| (*item.key(), *item.value())).collect(),
+            capacity: *self.capacity.read().unwrap(),
         }
     }
 
+    // write the persisted fields through, a no-op until rooms are loaded
+    async fn persist(&self) -> Result<(), RoomError> {
+        save(self.model()).await
+    }
+
+    // persisted before anything changes in memory
+    pub async fn update(&self, user_id: i32, param: RoomUpdateParam) -> Result<(), RoomError> {
+        self.require(user_id, Permission::EditSettings)?;
+        let RoomUpdateParam { name, access, password, invited, listed, capacity } = param;
+
+        let mut model = self.model();
+        if let Some(name) = name { model.name = validate_name(name)? }
+        if let Some(access) = access { model.access = access }
+        model.password = match (model.access, password) {
+            (RoomAccess::Password, None) if model.password.is_some() => model.password,
+            (access, password) => hash_password(access, password)?,
+        };
+        if let Some(invited) = invited {
+            if invited.len() > MAX_INVITED { return Err(RoomError::InvalidArgument("Too many invited users")) }
+            model.invited = invited;
+        }
+        if let Some(listed) = listed { model.listed = listed }
+        if let Some(capacity) = capacity { model.capacity = Some(validate_capacity(capacity)?) }
+        save(model.clone()).await?;
+
+        *self.name.write().unwrap() = model.name.clone();
+        *self.access.write().unwrap() = model.access;
+        *self.password.write().unwrap() = model.password;
+        self.invited.clear();
+        model.invited.into_iter().for_each(|id| { self.invited.insert(id); });
+        self.listed.store(model.listed, Ordering::Relaxed);
+        *self.capacity.write().unwrap() = model.capacity;
+
+        // members admitted earlier stay admitted
+        self.broadcast(&RoomEvent::Updated {
+            name: model.name,
+            access: model.access,
+            listed: model.listed,
+            capacity: self.capacity(),
+            by: user_id,
+        }).await;
+        Ok(())
+    }
+
+    pub fn role(&self, user_id: i32) -> RoomRole {
+        if user_id == self.host_id() { return RoomRole::Host }
+        self.roles.get(&user_id).map(|role| *role).unwrap_or_default()
+    }
+
+    // the role matrix plus per-user grants, mutes are checked where they apply
+    pub fn permits(&self, user_id: i32, permission: Permission) -> bool {
+        match permission {
+            Permission::Publish if self.publishers.contains(&user_id) => true,
+            Permission::ControlPlayback if self.playback_delegates.contains(&user_id) => true,
+            _ => self.role(user_id).allows(permission),
+        }
+    }
+
+    pub fn permissions(&self, user_id: i32) -> Vec<Permission> {
+        Permission::ALL.into_iter().filter(|p| self.permits(user_id, *p)).collect()
+    }
+
+    fn require(&self, user_id: i32, permission: Permission) -> Result<(), RoomError> {
+        self.permits(user_id, permission).then_some(()).ok_or(RoomError::Forbidden(permission))
+    }
+
+    // moderators act on members and viewers, only the host acts on moderators
+    fn check_moderate(&self, user_id: i32, target: i32) -> Result<(), RoomError> {
+        self.require(user_id, Permission::Moderate)?;
+        if target == self.host_id() { return Err(RoomError::InvalidArgument("The host cannot be moderated")) }
+        if target == user_id { return Err(RoomError::InvalidArgument("Cannot moderate yourself")) }
+        if self.role(target) == RoomRole::Moderator { self.require(user_id, Permission::EditSettings)? }
+        Ok(())
+    }
+
+    // host only, `Host` is handed over by a transfer instead
+    pub async fn set_role(&self, user_id: i32, target: i32, role: RoomRole) -> Result<(), RoomError> {
+        self.require(user_id, Permission::EditSettings)?;
+        if target == self.host_id() || role == RoomRole::Host {
+            return Err(RoomError::InvalidArgument("The host role cannot be assigned"));
+        }
+        let prev = match role {
+            RoomRole::Member => self.roles.remove(&target).map(|(_, role)| role),
+            _ => self.roles.insert(target, role),
+        };
+        if prev.unwrap_or_default() == role { return Ok(()) }
+        if let Err(e) = self.persist().await {
+            match prev {
+                Some(prev) => self.roles.insert(target, prev),
+                None => self.roles.remove(&target).map(|(_, role)| role),
+            };
+            return Err(e);
+        }
+        self.broadcast(&RoomEvent::Role { user_id: target, role, by: user_id }).await;
+        Ok(())
+    }
+
+    fn check_reason(reason: &Option<String>) -> Result<(), RoomError> {
+        if reason.as_ref().is_some_and(|r| r.chars().count() > MAX_REASON_LEN) {
+            return Err(RoomError::InvalidArgument("Reason too long"));
+        }
+        Ok(())
+    }
+
+    // drop every privilege of a member and close their socket
+    async fn expel(&self, target: i32, code: CloseCode, reason: &str) {
+        self.members.remove(&target);
+        self.playback_delegates.remove(&target);
+        if self.publishers.remove(&target).is_some() {
+            relay::release_track(&self.link, target);
+            self.broadcast(&RoomEvent::Tracks(self.tracks())).await;
+        }
+        relay::expel(&self.link, target, code, reason);
+        signal::expel(&self.link, target, code, reason);
+        if let Some((_, member)) = self.users.remove(&target) {
+            member.conns.iter().for_each(|conn| conn.hang_up(code, reason));
+            self.broadcast(&RoomEvent::Left { user_id: target, name: member.name }).await;
+        }
+    }
+
+    async fn announce_moderation(&self, action: ModerationAction, user_id: i32, by: i32, reason: Option<String>) {
+        self.broadcast(&RoomEvent::Moderation { action, user_id, by, reason }).await;
+    }
+
+    pub async fn kick(&self, user_id: i32, target: i32, reason: Option<String>) -> Result<(), RoomError> {
+        self.check_moderate(user_id, target)?;
+        Self::check_reason(&reason)?;
+        if !self.is_member(target) && self.contains_user(target).is_err() { return Err(RoomError::UserNotFound) }
+
+        self.expel(target, CloseCode::Kicked, reason.as_deref().unwrap_or("Kicked by the host")).await;
+        self.announce_moderation(ModerationAction::Kick, target, user_id, reason).await;
+        Ok(())
+    }
+
+    // kicks as well, the ban outlives restarts
+    pub async fn ban(&self, user_id: i32, target: i32, reason: Option<String>) -> Result<(), RoomError> {
+        self.check_moderate(user_id, target)?;
+        Self::check_reason(&reason)?;
+        if !self.banned.insert(target) { return Ok(()) }
+        if let Err(e) = self.persist().await {
+            self.banned.remove(&target);
+            return Err(e);
+        }
+
+        self.expel(target, CloseCode::Banned, reason.as_deref().unwrap_or("Banned by the host")).await;
+        self.announce_moderation(ModerationAction::Ban, target, user_id, reason).await;
+        Ok(())
+    }
+
+    pub async fn unban(&self, user_id: i32, target: i32) -> Result<(), RoomError> {
+        self.check_moderate(user_id, target)?;
+        if self.banned.remove(&target).is_none() { return Ok(()) }
+        if let Err(e) = self.persist().await {
+            self.banned.insert(target);
+            return Err(e);
+        }
+        self.announce_moderation(ModerationAction::Unban, target, user_id, None).await;
+        Ok(())
+    }
+
+    pub async fn mute(&self, user_id: i32, target: i32, reason: Option<String>) -> Result<(), RoomError> {
+        self.check_moderate(user_id, target)?;
+        Self::check_reason(&reason)?;
+        if !self.is_member(target) && self.contains_user(target).is_err() { return Err(RoomError::UserNotFound) }
+        if self.muted.insert(target) {
+            self.announce_moderation(ModerationAction::Mute, target, user_id, reason).await;
+        }
+        Ok(())
+    }
+
+    pub async fn unmute(&self, user_id: i32, target: i32) -> Result<(), RoomError> {
+        self.check_moderate(user_id, target)?;
+        if self.muted.remove(&target).is_some() {
+            self.announce_moderation(ModerationAction::Unmute, target, user_id, None).await;
+        }
+        Ok(())
+    }
+
     pub fn contains_user(&self, user_id: i32) -> Result<(), RoomError> {
         self.users.contains_key(&user_id).then_some(()).ok_or(RoomError::UserNotFound)
     }
 
+    // the host and granted co-hosts publish, every other member views;
+    // stream sockets check it on connect since their tokens outlive kicks and bans
+    pub fn stream_role(&self, user_id: i32) -> Result<StreamRole, RoomError> {
+        if user_id == self.host_id() { return Ok(StreamRole::Publisher) }
+        if self.banned.contains(&user_id) { return Err(RoomError::Banned) }
+        self.contains_user(user_id)?;
+        Ok(if self.can_publish(user_id) { StreamRole::Publisher } else { StreamRole::Viewer })
+    }
+
+    pub fn can_publish(&self, user_id: i32) -> bool {
+        self.permits(user_id, Permission::Publish)
+    }
+
+    // host first, co-hosts by user id
+    pub fn publishers(&self) -> Vec<i32> {
+        let mut ret: Vec<i32> = self.publishers.iter().map(|id| *id).collect();
+        ret.sort_unstable();
+        ret.insert(0, self.host_id());
+        ret
+    }
+
+    pub fn tracks(&self) -> Vec<StreamTrack> {
+        self.publishers().into_iter()
+            .map(|publisher| StreamTrack {
+                publisher,
+                host: publisher == self.host_id(),
+                mime: relay::track_mime(&self.link, publisher),
+            })
+            .collect()
+    }
+
+    pub async fn grant_publisher(&self, user_id: i32, target: i32) -> Result<(), RoomError> {
+        self.require(user_id, Permission::EditSettings)?;
+        if target == self.host_id() { return Err(RoomError::InvalidArgument("Host always publishes")) }
+        self.contains_user(target)?;
+        if self.publishers.insert(target) {
+            self.broadcast(&RoomEvent::Tracks(self.tracks())).await;
+        }
+        Ok(())
+    }
+
+    // the revoked publisher's relayed track is torn down right away
+    pub async fn revoke_publisher(&self, user_id: i32, target: i32) -> Result<(), RoomError> {
+        self.require(user_id, Permission::EditSettings)?;
+        if self.publishers.remove(&target).is_some() {
+            relay::release_track(&self.link, target);
+            self.broadcast(&RoomEvent::Tracks(self.tracks())).await;
+        }
+        Ok(())
+    }
+
     pub fn share_link(&self) -> String {
         self.link.as_str().to_string()
     }
@@ -77,7 +579,7 @@ impl Room {
     }
     
     pub fn host_id(&self) -> i32 {
-        self.host_id
+        self.host_id.load(Ordering::Relaxed)
     }
     
     pub fn name(&self) -> String {
@@ -88,24 +590,233 @@ impl Room {
         self.host_name.read().unwrap().clone()
     }
      
-    pub async fn join(&self, user_id: i32, tx: mpsc::Sender<Arc<ChatMessage>>) {
-        self.users.insert(user_id, tx);
+    pub fn playback(&self) -> PlaybackState {
+        self.playback.read().unwrap().clone()
     }
     
-    pub async fn leave(&self, user_id: i32) -> Result<(), RoomError> {
+    // the library item currently loaded for playback
+    pub fn source(&self) -> Option<MediaItem> {
+        let media_id = self.playback.read().unwrap().media_id.clone()?;
+        media::get_media(&media_id).ok()
+    }
+     
+    pub fn thumbnail(&self) -> Option<Arc<Thumbnail>> {
+        self.thumbnail.read().unwrap().clone()
+    }
+
+    pub fn thumbnail_url(&self) -> Option<String> {
+        let thumbnail = self.thumbnail()?;
+        Some(format!("/room/thumbnail?room={}&v={}", self.link, thumbnail.version()))
+    }
+
+    pub fn set_thumbnail(&self, user_id: i32, thumbnail: Thumbnail) -> Result<(), RoomError> {
+        self.require(user_id, Permission::EditSettings)?;
+        *self.thumbnail.write().unwrap() = Some(Arc::new(thumbnail));
+        Ok(())
+    }
+    
+    // tracks uploaded for the media currently loaded
+    pub fn subtitles(&self) -> Vec<SubtitleTrack> {
+        let Some(media_id) = self.playback.read().unwrap().media_id.clone() else { return vec![] };
+        let mut ret: Vec<SubtitleTrack> = self.subtitles.iter()
+            .filter(|track| track.media_id == media_id)
+            .map(|track| track.value().as_ref().clone())
+            .collect();
+        ret.sort_by_key(|track| track.id);
+        ret
+    }
+
+    pub fn subtitle(&self, id: u32) -> Option<Arc<SubtitleTrack>> {
+        self.subtitles.get(&id).map(|track| track.value().clone())
+    }
+
+    pub async fn add_subtitle(
+        &self, user_id: i32, label: String,
+        lang: Option<String>, data: &[u8]
+    ) -> Result<SubtitleTrack, RoomError> {
+        self.require(user_id, Permission::ControlPlayback)?;
+        let media_id = self.playback.read().unwrap().media_id.clone()
+            .ok_or(RoomError::InvalidArgument("No media loaded"))?;
+        let uploaded = self.subtitles.iter().filter(|track| track.media_id == media_id).count();
+        if uploaded >= MAX_SUBTITLE_TRACKS { return Err(RoomError::InvalidArgument("Too many subtitle tracks")) }
+
+        let track = subtitle::new_track(&self.link, media_id, label, lang, data)?;
+        self.subtitles.insert(track.id, Arc::new(track.clone()));
+        self.broadcast(&RoomEvent::Subtitles(self.subtitles())).await;
+        Ok(track)
+    }
+
+    // a removed track that is showing is turned off for everyone
+    pub async fn remove_subtitle(&self, user_id: i32, id: u32) -> Result<(), RoomError> {
+        self.require(user_id, Permission::ControlPlayback)?;
+        self.subtitles.remove(&id).ok_or(RoomError::InvalidArgument("Unknown subtitle track"))?;
+        let state = {
+            let mut playback = self.playback.write().unwrap();
+            (playback.subtitle == Some(id)).then(|| {
+                playback.subtitle = None;
+                playback.clone()
+            })
+        };
+        if let Some(state) = state {
+            self.broadcast(&RoomEvent::Playback(state)).await;
+        }
+        self.broadcast(&RoomEvent::Subtitles(self.subtitles())).await;
+        Ok(())
+    }
+    
+    // connected members, earliest first
+    pub fn member_list(&self) -> Vec<RoomMember> {
+        let mut ret: Vec<RoomMember> = self.users.iter()
+            .map(|item| RoomMember {
+                user_id: *item.key(),
+                name: item.name.clone(),
+                role: self.role(*item.key()),
+                joined_at: item.joined_at,
+                last_seen: item.last_seen,
+            })
+            .collect();
+        ret.sort_by_key(|member| (member.joined_at, member.user_id));
+        ret
+    }
+
+    // connections matching `filter(user_id, conn_id)`
+    fn connections(&self, filter: impl Fn(i32, ConnId) -> bool) -> Vec<Conn> {
+        self.users.iter()
+            .flat_map(|item| item.conns.iter()
+                .filter(|conn| filter(*item.key(), conn.id))
+                .cloned()
+                .collect::<Vec<_>>())
+            .collect()
+    }
+
+    // late joiners get the current playback state, track list and members right away,
+    // only a user's first connection is announced, `Err` carries why the socket was turned away
+    pub async fn join(
+        &self, user_id: i32, name: String, tx: mpsc::Sender<Arc<RoomMessage>>
+    ) -> Result<(ConnId, Hangup), (CloseCode, String)> {
+        // a handler may still hold a room that has just been closed
+        let closed = self.closed.borrow().clone();
+        if let Some(reason) = closed { return Err((CloseCode::RoomClosed, reason)) }
+        // checked at upgrade already, this catches sockets racing for the last seat
+        let seats = self.seats.lock().unwrap();
+        self.check_capacity(user_id).map_err(|e| (CloseCode::RoomFull, e.to_string()))?;
+        let conn = CONN_ID.fetch_add(1, Ordering::Relaxed);
+        let mut member = self.users.entry(user_id).or_insert_with(|| Member {
+            name: name.clone(),
+            joined_at: Utc::now(),
+            last_seen: Utc::now(),
+            conns: vec![],
+        });
+        let (hangup, hung_up) = watch::channel(None);
+        member.conns.push(Conn { id: conn, tx: tx.clone(), hangup });
+        member.last_seen = Utc::now();
+        let (joined_at, last_seen, first) = (member.joined_at, member.last_seen, member.conns.len() == 1);
+        drop(member);
+        drop(seats);
+        self.touch();
+        let msg = RoomMessage::event(&self.link, &RoomEvent::Playback(self.playback()));
+        let _ = tx.send(Arc::new(msg)).await;
+        let msg = RoomMessage::event(&self.link, &RoomEvent::Tracks(self.tracks()));
+        let _ = tx.send(Arc::new(msg)).await;
+        let msg = RoomMessage::event(&self.link, &RoomEvent::Members(self.member_list()));
+        let _ = tx.send(Arc::new(msg)).await;
+        if !first { return Ok((conn, hung_up)) }
+
+        let member = RoomMember { user_id, name, role: self.role(user_id), joined_at, last_seen };
+        let msg = Arc::new(RoomMessage::event(&self.link, &RoomEvent::Joined(member)));
+        deliver(&self.connections(|id, _| id != user_id), &msg);
+        Ok((conn, hung_up))
+    }
+    
+    // the user leaves with their last connection
+    pub async fn leave(&self, user_id: i32, conn: ConnId) -> Result<(), RoomError> {
         self.contains_user(user_id)?;
-        self.users.remove(&user_id);
+        self.touch();
+        let left = self.users.remove_if_mut(&user_id, |_, member| {
+            member.conns.retain(|c| c.id != conn);
+            member.conns.is_empty()
+        });
+        if let Some((_, member)) = left {
+            self.broadcast(&RoomEvent::Left { user_id, name: member.name }).await;
+        }
         Ok(())
     }
 
-    pub async fn sync_message(&self, author_id: i32, content: ChatMessageContent) -> Result<(), RoomError> {
+    // the author's other connections get a copy, `from` already shows it
+    pub async fn sync_message(&self, author_id: i32, from: Option<ConnId>, content: ChatMessageContent) -> Result<(), RoomError> {
         self.contains_user(author_id)?;
-        let msg = Arc::new(ChatMessage::new(author_id, self.share_link(), content));
-        for item in self.users.iter() {
-            if item.key() == &author_id { continue }
-            item.value().send(msg.clone()).await.map_err(|_| RoomError::InternalError)?;
+        if self.muted.contains(&author_id) { return Err(RoomError::Muted) }
+        let permission = match content {
+            ChatMessageContent::File { .. } => Permission::PostFile,
+            _ => Permission::Chat,
+        };
+        self.require(author_id, permission)?;
+        let msg = Arc::new(RoomMessage::Chat(ChatMessage::new(author_id, self.share_link(), content)));
+        let conns = self.connections(|id, conn| Some(conn) != from && (from.is_some() || id != author_id));
+        deliver(&conns, &msg);
+        
+        Ok(())
+    }
+    
+    // send an event to every connection, closed channels are skipped
+    pub async fn broadcast(&self, event: &RoomEvent) {
+        let msg = Arc::new(RoomMessage::event(&self.link, event));
+        deliver(&self.connections(|_, _| true), &msg);
+    }
+    
+    // publishers get a throttled health summary pushed as viewers report
+    pub async fn report_stats(&self, user_id: i32, report: StatsReport) -> Result<(), RoomError> {
+        self.contains_user(user_id)?;
+        self.telemetry.report(user_id, report).map_err(RoomError::InvalidArgument)?;
+        if !self.telemetry.should_push() { return Ok(()) }
+
+        let msg = Arc::new(RoomMessage::event(&self.link, &RoomEvent::Health(self.telemetry.summary())));
+        deliver(&self.connections(|id, _| self.can_publish(id)), &msg);
+        Ok(())
+    }
+
+    pub fn stream_health(&self, user_id: i32) -> Result<StreamHealth, RoomError> {
+        self.require(user_id, Permission::Publish)?;
+        Ok(self.telemetry.summary())
+    }
+    
+    pub async fn control_playback(&self, user_id: i32, cmd: PlaybackCommand) -> Result<(), RoomError> {
+        self.contains_user(user_id)?;
+        // handing out control is a room setting
+        let permission = match cmd {
+            PlaybackCommand::Delegate { .. } | PlaybackCommand::Revoke { .. } => Permission::EditSettings,
+            _ => Permission::ControlPlayback,
+        };
+        self.require(user_id, permission)?;
+        
+        match cmd {
+            PlaybackCommand::Delegate { user_id: target } => {
+                self.contains_user(target)?;
+                self.playback_delegates.insert(target);
+                return Ok(())
+            },
+            PlaybackCommand::Revoke { user_id: target } => {
+                self.playback_delegates.remove(&target);
+                return Ok(())
+            },
+            PlaybackCommand::Load { ref media_id } => {
+                media::get_media(media_id).map_err(|_| RoomError::InvalidArgument("Unknown media"))?;
+            },
+            PlaybackCommand::Subtitle { track: Some(Some(id)), .. } => {
+                let media_id = self.playback.read().unwrap().media_id.clone();
+                if self.subtitle(id).is_none_or(|track| Some(&track.media_id) != media_id.as_ref()) {
+                    return Err(RoomError::InvalidArgument("Unknown subtitle track"));
+                }
+            },
+            _ => {},
         }
         
+        let state = {
+            let mut playback = self.playback.write().unwrap();
+            playback.apply(&cmd).map_err(RoomError::InvalidArgument)?;
+            playback.clone()
+        };
+        self.broadcast(&RoomEvent::Playback(state)).await;
         Ok(())
     }
 }
@@ -119,71 +830,262 @@ impl PartialEq for Room {
 #[derive(Clone, Debug)]
 pub struct Rooms {
     rooms: Arc<DashMap<String, Room>>, // link -> Room
-    release_timers: Arc<DashMap<String, AtomicI64>>, // link -> release_time
-    hosts: Arc<DashMap<i32, Vec<String>>>, // host_id -> link(s)
+    // link -> released_at, for deleted rooms; kept in memory only, so after a restart
+    // a deleted link answers `RoomNotFound` and may in principle be handed out again
+    released: Arc<DashMap<String, i64>>,
+    dormant: Arc<DashMap<String, (RoomModel, String)>>, // link -> (model, host name) of unloaded rooms
+    hosts: Arc<DashMap<i32, Vec<String>>>, // host_id -> link(s), dormant rooms included
 }
 
 impl Rooms {
     fn new() -> Self {
         Self {
             rooms: Arc::new(DashMap::new()),
-            release_timers: Arc::new(DashMap::new()),
+            released: Arc::new(DashMap::new()),
+            dormant: Arc::new(DashMap::new()),
             hosts: Arc::new(DashMap::new()),
         }
     }
 
-    fn create(&self, host_id: i32, host_name: String, room_name: String) -> Room {
+    fn insert(&self, room: Room) {
+        let link = room.share_link();
+        self.hosts.entry(room.host_id()).or_default().push(link.clone());
+        self.rooms.insert(link, room);
+    }
+
+    // unlink a room from every index, the caller closes it
+    fn remove(&self, room_link: &str) -> Option<Room> {
+        let (_, room) = self.rooms.remove(room_link)?;
+        self.dormant.remove(room_link);
+        self.unreserve(room.host_id(), room_link);
+        self.released.insert(room_link.to_string(), Utc::now().timestamp());
+        Some(room)
+    }
+
+    async fn release(&self, room_link: &str, reason: &str) -> Option<Room> {
+        let room = self.remove(room_link)?;
+        if let Some(repo) = REPO.get()
+            && let Err(e) = repo.delete_room(room_link).await {
+            eprintln!("Failed to delete room {room_link}: {e}");
+        }
+        room.close(reason).await;
+        // after `close`, so that relay viewers see the reason before their tracks go away
+        relay::release(room_link);
+        Some(room)
+    }
+
+    // an idle room leaves memory but keeps its row and its host's quota slot,
+    // the next lookup of the link brings it back, without its members
+    async fn unload(&self, room_link: &str, reason: &str) -> Option<Room> {
+        let Entry::Occupied(entry) = self.rooms.entry(room_link.to_string()) else { return None };
+        self.dormant.insert(room_link.to_string(), (entry.get().model(), entry.get().host_name()));
+        let room = entry.remove();
+        room.close(reason).await;
+        relay::release(room_link);
+        Some(room)
+    }
+
+    // one reaper pass, rooms are closed concurrently so a stuck socket cannot hold up the rest
+    fn reap(&self, cfg: &RoomConfig) {
+        let now = Utc::now().timestamp();
+        self.released.retain(|_, released_at| now - *released_at < cfg.tombstone_ttl);
+
+        let idle: Vec<String> = self.rooms.iter()
+            .filter(|item| now - item.value().idle_since() > cfg.idle_timeout)
+            .map(|item| item.key().clone())
+            .collect();
+        for room_link in idle {
+            let rooms = self.clone();
+            tokio::spawn(async move {
+                if rooms.unload(&room_link, "Room closed after being idle").await.is_some() {
+                    println!("Room {room_link} unloaded");
+                }
+            });
+        }
+    }
+
+    // restore persisted rooms, their idle timers start over
+    // new rooms are written through even if the stored ones cannot be read
+    async fn load(&self, repo: Arc<dyn Repository>) -> Result<usize, RoomError> {
+        let _ = REPO.set(repo.clone());
+        let models = repo.find_rooms().await.map_err(|e| {
+            eprintln!("Failed to read rooms: {e}");
+            RoomError::InternalError
+        })?;
+        let mut loaded = 0;
+        for model in models {
+            let Ok(host) = user::get_user_by_id(repo.clone(), model.host_id).await else {
+                eprintln!("Room {} skipped, host {} not found", model.link, model.host_id);
+                continue;
+            };
+            self.insert(Room::new(model, host.name));
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    // the old host stays on as a moderator and keeps publishing
+    async fn transfer(&self, room_link: &str, user_id: i32, target: i32, target_name: String) -> Result<Room, RoomError> {
+        let room = self.get_room_by_link(room_link)?;
+        room.require(user_id, Permission::EditSettings)?;
+        let old = room.host_id();
+        if target == old { return Err(RoomError::InvalidArgument("Already the host")) }
+        if !room.is_member(target) { return Err(RoomError::UserNotFound) }
+        self.reserve(target, room_link)?;
+
+        let mut model = room.model();
+        model.host_id = target;
+        model.roles.retain(|(id, _)| *id != target && *id != old);
+        model.roles.push((old, RoomRole::Moderator));
+        if let Err(e) = save(model).await {
+            self.unreserve(target, room_link);
+            return Err(e);
+        }
+
+        room.host_id.store(target, Ordering::Relaxed);
+        *room.host_name.write().unwrap() = target_name.clone();
+        room.roles.remove(&target);
+        room.roles.insert(old, RoomRole::Moderator);
+        room.members.insert(old);
+        room.publishers.remove(&target);
+        room.publishers.insert(old);
+        room.playback_delegates.remove(&target);
+
+        self.unreserve(old, room_link);
+
+        room.broadcast(&RoomEvent::HostChanged { host_id: target, host_name: target_name, by: user_id }).await;
+        room.broadcast(&RoomEvent::Tracks(room.tracks())).await;
+        Ok(room)
+    }
+
+    async fn delete(&self, room_link: &str, user_id: i32, reason: Option<String>) -> Result<(), RoomError> {
+        let room = self.get_room_by_link(room_link)?;
+        room.require(user_id, Permission::EditSettings)?;
+        Room::check_reason(&reason)?;
+        let reason = reason.unwrap_or_else(|| "Room deleted by the host".to_string());
+        self.release(room_link, &reason).await;
+        Ok(())
+    }
+
+    // checks the quota and takes the slot under one entry lock, concurrent creates cannot both fit
+    fn reserve(&self, host_id: i32, room_link: &str) -> Result<(), RoomError> {
+        let mut links = self.hosts.entry(host_id).or_default();
+        if !is_admin(host_id) && links.len() >= ROOM_CFG.max_hosted_rooms {
+            return Err(RoomError::QuotaExceeded(ROOM_CFG.max_hosted_rooms));
+        }
+        links.push(room_link.to_string());
+        Ok(())
+    }
+
+    fn unreserve(&self, host_id: i32, room_link: &str) {
+        self.hosts.remove_if_mut(&host_id, |_, links| {
+            links.retain(|l| l != room_link);
+            links.is_empty()
+        });
+    }
+
+    async fn create(&self, host_id: i32, host_name: String, room_name: String, param: RoomAccessParam) -> Result<Room, RoomError> {
+        let RoomAccessParam { access, password, invited, listed, capacity } = param;
+        let room_name = validate_name(room_name)?;
+        let capacity = capacity.map(validate_capacity).transpose()?;
+        let password = hash_password(access, password)?;
+        if invited.len() > MAX_INVITED { return Err(RoomError::InvalidArgument("Too many invited users")) }
 
-        let mut res = self.hosts.entry(host_id).or_default();
         let new_link = loop {
             let link = gen_rand_string(ROOM_SHARE_LINK_LEN);
-            if !self.rooms.contains_key(&link) {
+            if !self.rooms.contains_key(&link) && !self.dormant.contains_key(&link) && !self.released.contains_key(&link) {
                 break link;
             }
         };
         
-        res.push(new_link.clone());
-        drop(res);
+        self.reserve(host_id, &new_link)?;
+        let model = RoomModel {
+            link: new_link,
+            host_id,
+            name: room_name,
+            created_at: Utc::now(),
+            access,
+            password,
+            invited,
+            listed,
+            banned: vec![],
+            roles: vec![],
+            capacity,
+        };
+        if let Some(repo) = REPO.get()
+            && repo.create_room(model.clone()).await.is_err() {
+            self.unreserve(host_id, &model.link);
+            return Err(RoomError::InternalError);
+        }
+        let new_room = Room::new(model, host_name);
         
-        let new_room = Room::new(host_id, host_name, room_name, new_link.clone());
-        let release_timer = AtomicI64::new(Utc::now().timestamp());
-        self.rooms.insert(new_link.clone(), new_room.clone());
-        self.release_timers.insert(new_link, release_timer);
+        self.rooms.insert(new_room.share_link(), new_room.clone());
         
         println!("CurrRooms: {:?}", self.rooms);
         println!("CurrHosts: {:?}", self.hosts);
         
-        new_room
+        Ok(new_room)
     }
 
-    // get room by link, if room is empty and expired, release room
     fn get_room_by_link(&self, room_link: &str) -> Result<Room, RoomError> {
-        let room = self.rooms.get(room_link)
-            .map(|r| r.clone()).ok_or(RoomError::RoomNotFound)?;
-        
-        if room.user_len() != 0 { return Ok(room) }
-        
-        // TODO! change to Async approaches
-        let now = Utc::now().timestamp();
-        if now > self.release_timers.get(room_link).unwrap().load(Ordering::Relaxed) + ROOM_RELEASE_DURATION_S {
-            // release room
-            let host_id = room.host_id();
-            drop(room);
-            
-            self.rooms.remove(room_link);
-            println!("room removed");
-            self.release_timers.remove(room_link);
-            println!("timer removed");
-            if let Some(mut entry) = self.hosts.get_mut(&host_id) {
-                entry.value_mut().retain(|r| r != room_link);
+        if let Some(room) = self.rooms.get(room_link) {
+            return Ok(room.clone());
+        }
+        // revived under the entry lock, concurrent lookups all get the same room
+        if self.dormant.contains_key(room_link) {
+            match self.rooms.entry(room_link.to_string()) {
+                Entry::Occupied(entry) => return Ok(entry.get().clone()),
+                Entry::Vacant(entry) => if let Some((_, (model, host_name))) = self.dormant.remove(room_link) {
+                    let room = Room::new(model, host_name);
+                    println!("Room {room_link} reloaded");
+                    return Ok(entry.insert(room).clone());
+                },
             }
-            println!("hosts updated");
+        }
+        if self.released.contains_key(room_link) {
             return Err(RoomError::RoomReleased);
-        };
-        
-        Ok(room)
+        }
+        Err(RoomError::RoomNotFound)
     }
     
+    // the directory, member counts are live so a page boundary may shift between requests
+    fn list(&self, param: &ListParam) -> Result<(Vec<Room>, Option<String>), RoomError> {
+        let query = param.query.trim().to_lowercase();
+        let after = param.cursor.as_deref().map(|c| decode_cursor(c, param.sort)).transpose()?;
+        let limit = param.limit.clamp(1, MAX_LIST_LIMIT);
+
+        let mut rooms: Vec<(i64, Room)> = self.rooms.iter()
+            .map(|item| item.value().clone())
+            .filter(|room| room.is_listed())
+            .filter(|room| match param.status {
+                RoomStatus::All => true,
+                RoomStatus::Live => room.user_len() > 0,
+                RoomStatus::Empty => room.user_len() == 0,
+            })
+            .filter(|room| query.is_empty()
+                || room.name().to_lowercase().contains(&query)
+                || room.host_name().to_lowercase().contains(&query))
+            .map(|room| {
+                let key = match param.sort {
+                    RoomSort::Created => room.created_at().timestamp_millis(),
+                    RoomSort::Members => room.user_len() as i64,
+                };
+                (key, room)
+            })
+            .collect();
+        // descending by key, the link breaks ties
+        rooms.sort_by(|(a, ra), (b, rb)| b.cmp(a).then_with(|| ra.link.cmp(&rb.link)));
+        if let Some((key, link)) = after {
+            rooms.retain(|(k, room)| *k < key || (*k == key && room.link.as_str() > link.as_str()));
+        }
+
+        let next_cursor = (rooms.len() > limit)
+            .then(|| &rooms[limit - 1])
+            .map(|(key, room)| encode_cursor(param.sort, *key, &room.link));
+        rooms.truncate(limit);
+        Ok((rooms.into_iter().map(|(_, room)| room).collect(), next_cursor))
+    }
+
     fn hosted_rooms(&self, host_id: i32) -> Vec<Room> {
         let rooms = self.hosts.get(&host_id)
             .map(|r| r.clone()).unwrap_or_default();
@@ -218,8 +1120,100 @@ pub fn related_to(user_id: i32) -> Vec<Room> {
     ret
 }
 
-pub fn create_host_by(host_id: i32, host_name: String, name: String) -> Room {
-    rooms().create(host_id, host_name, name)
+pub fn list(param: &ListParam) -> Result<(Vec<Room>, Option<String>), RoomError> {
+    rooms().list(param)
+}
+
+pub async fn transfer(room_link: &str, user_id: i32, target: i32, target_name: String) -> Result<Room, RoomError> {
+    rooms().transfer(room_link, user_id, target, target_name).await
+}
+
+pub async fn delete(room_link: &str, user_id: i32, reason: Option<String>) -> Result<(), RoomError> {
+    rooms().delete(room_link, user_id, reason).await
+}
+
+pub async fn load(repo: Arc<dyn Repository>) -> Result<usize, RoomError> {
+    rooms().load(repo).await
+}
+
+pub fn spawn_reaper(cfg: &'static RoomConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(cfg.reap_interval));
+        loop {
+            interval.tick().await;
+            rooms().reap(cfg);
+        }
+    })
+}
+
+pub async fn create_host_by(host_id: i32, host_name: String, name: String, param: RoomAccessParam) -> Result<Room, RoomError> {
+    rooms().create(host_id, host_name, name, param).await
+}
+
+async fn save(model: RoomModel) -> Result<(), RoomError> {
+    let Some(repo) = REPO.get() else { return Ok(()) };
+    repo.update_room(model).await.map_err(|_| RoomError::InternalError)
+}
+
+pub fn is_admin(user_id: i32) -> bool {
+    ROOM_CFG.admins.contains(&user_id)
+}
+
+fn validate_capacity(capacity: u32) -> Result<u32, RoomError> {
+    if capacity == 0 || capacity as usize > ROOM_CFG.max_members {
+        return Err(RoomError::InvalidArgument("Capacity must be between 1 and the server limit"));
+    }
+    Ok(capacity)
+}
+
+fn validate_name(name: String) -> Result<String, RoomError> {
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(RoomError::InvalidArgument("Room name must be 1 to 64 characters long"));
+    }
+    Ok(name)
+}
+
+// only password rooms keep a hash
+fn hash_password(access: RoomAccess, password: Option<String>) -> Result<Option<String>, RoomError> {
+    match (access, password) {
+        (RoomAccess::Password, Some(p)) if (4..=64).contains(&p.chars().count()) => {
+            Ok(Some(user::bcrypt_password(&p, bcrypt::DEFAULT_COST)))
+        },
+        (RoomAccess::Password, _) => Err(RoomError::InvalidArgument("Password must be 4 to 64 characters long")),
+        _ => Ok(None),
+    }
+}
+
+fn cursor_tag(sort: RoomSort) -> &'static str {
+    match sort {
+        RoomSort::Created => "c",
+        RoomSort::Members => "m",
+    }
+}
+
+// opaque to clients: `{sort}:{key}:{link}` of the last room on the page
+fn encode_cursor(sort: RoomSort, key: i64, link: &str) -> String {
+    BASE64.encode(format!("{}:{key}:{link}", cursor_tag(sort)))
+}
+
+fn decode_cursor(cursor: &str, sort: RoomSort) -> Result<(i64, String), RoomError> {
+    const INVALID: RoomError = RoomError::InvalidArgument("Invalid cursor");
+    let raw = BASE64.decode(cursor).ok().and_then(|raw| String::from_utf8(raw).ok()).ok_or(INVALID)?;
+    let mut parts = raw.splitn(3, ':');
+    let (Some(tag), Some(key), Some(link)) = (parts.next(), parts.next(), parts.next()) else { return Err(INVALID) };
+    if tag != cursor_tag(sort) { return Err(INVALID) }
+    Ok((key.parse().map_err(|_| INVALID)?, link.to_string()))
+}
+
+// never waits on a socket, a full queue means the peer stopped reading
+// and its handler is told to hang up
+fn deliver(conns: &[Conn], msg: &Arc<RoomMessage>) {
+    for conn in conns {
+        if let Err(mpsc::error::TrySendError::Full(_)) = conn.tx.try_send(msg.clone()) {
+            conn.hang_up(CloseCode::Timeout, "Too slow to keep up");
+        }
+    }
 }
 
 fn gen_rand_string(len: usize) -> String {
@@ -231,3 +1225,24 @@ fn gen_rand_string(len: usize) -> String {
         .collect()
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips_for_its_sort() {
+        let cursor = encode_cursor(RoomSort::Members, 12, "a:b");
+        assert_eq!(decode_cursor(&cursor, RoomSort::Members).unwrap(), (12, "a:b".to_string()));
+        assert!(decode_cursor(&cursor, RoomSort::Created).is_err());
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        for raw in ["c:12", "c:x:link", "x:12:link", ""] {
+            assert!(decode_cursor(&BASE64.encode(raw), RoomSort::Created).is_err(), "{raw}");
+        }
+        assert!(decode_cursor("not base64!", RoomSort::Created).is_err());
+        assert!(decode_cursor(&BASE64.encode([0xff, 0xfe]), RoomSort::Created).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU32, Ordering};
+use chrono::Utc;
+
+use crate::model::SubtitleTrack;
+use super::room::RoomError;
+
+const MAX_SUBTITLE_BYTES: usize = 1024 * 1024;
+
+static ID: AtomicU32 = AtomicU32::new(1);
+
+// SRT is converted to WebVTT on upload
+pub fn new_track(
+    room_link: &str, media_id: String, label: String,
+    lang: Option<String>, data: &[u8]
+) -> Result<SubtitleTrack, RoomError> {
+    if data.len() > MAX_SUBTITLE_BYTES { return Err(RoomError::InvalidArgument("Subtitle file too large")) }
+    let text = std::str::from_utf8(data).map_err(|_| RoomError::InvalidArgument("Subtitles must be UTF-8"))?;
+    let vtt = to_vtt(text).ok_or(RoomError::InvalidArgument("Unrecognized subtitle format"))?;
+
+    let id = ID.fetch_add(1, Ordering::Relaxed);
+    Ok(SubtitleTrack {
+        id,
+        media_id,
+        label,
+        lang,
+        url: format!("/room/subtitle?room={room_link}&id={id}"),
+        created_at: Utc::now(),
+        vtt,
+    })
+}
+
+// WebVTT is passed through, anything else is read as SRT
+fn to_vtt(text: &str) -> Option<String> {
+    let text = text.trim_start_matches('\u{FEFF}').replace("\r\n", "\n").replace('\r', "\n");
+    let is_vtt = text.strip_prefix("WEBVTT")
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t', '\n']));
+    if is_vtt { return Some(text) }
+    srt_to_vtt(&text)
+}
+
+// `HH:MM:SS,mmm`, hours may be longer than two digits and `.` is tolerated
+fn srt_timestamp(s: &str) -> Option<String> {
+    let (hms, ms) = s.trim().split_once([',', '.'])?;
+    let mut parts = hms.split(':');
+    let (h, m, sec) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || ms.len() != 3 { return None }
+
+    let h: u32 = h.parse().ok()?;
+    let m: u32 = m.parse().ok()?;
+    let sec: u32 = sec.parse().ok()?;
+    let ms: u32 = ms.parse().ok()?;
+    if m > 59 || sec > 59 { return None }
+    Some(format!("{h:02}:{m:02}:{sec:02}.{ms:03}"))
+}
+
+// SRT styling beyond <b>, <i> and <u> has no WebVTT equivalent
+fn strip_font_tags(line: &str) -> String {
+    let mut ret = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('<') {
+        ret.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let lower = tag.to_ascii_lowercase();
+        match tag.find('>') {
+            Some(end) if lower.starts_with("<font") || lower.starts_with("</font") => rest = &tag[end + 1..],
+            _ => {
+                ret.push('<');
+                rest = &tag[1..];
+            },
+        }
+    }
+    ret.push_str(rest);
+    ret.replace("-->", "->")
+}
+
+fn srt_to_vtt(text: &str) -> Option<String> {
+    let mut ret = String::from("WEBVTT\n");
+    let mut cues = 0;
+    for block in text.split("\n\n").map(str::trim).filter(|b| !b.is_empty()) {
+        let mut lines = block.lines();
+        let mut timing = lines.next()?;
+        if !timing.contains("-->") {
+            // cue number
+            let Some(line) = lines.next() else { continue };
+            timing = line;
+        }
+
+        let Some((start, end)) = timing.split_once("-->") else { continue };
+        // SRT may append coordinates after the end time
+        let end = end.split_whitespace().next().unwrap_or_default();
+        let (Some(start), Some(end)) = (srt_timestamp(start), srt_timestamp(end)) else { continue };
+
+        let _ = write!(ret, "\n{start} --> {end}\n");
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let _ = writeln!(ret, "{}", strip_font_tags(line));
+        }
+        cues += 1;
+    }
+    (cues > 0).then_some(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRT: &str = "1\n00:00:01,000 --> 00:00:02,500\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+    const VTT: &str = "WEBVTT\n\n00:00:01.000 --> 00:00:02.500\nHello\n\n00:00:03.000 --> 00:00:04.000\nWorld\n";
+
+    #[test]
+    fn srt_timestamps_use_a_dot() {
+        assert_eq!(to_vtt(SRT).as_deref(), Some(VTT));
+        assert_eq!(srt_timestamp("123:04:05,006").as_deref(), Some("123:04:05.006"));
+        assert_eq!(srt_timestamp("00:60:00,000"), None);
+        assert_eq!(srt_timestamp("00:00:00,00"), None);
+    }
+
+    #[test]
+    fn crlf_and_bom_are_normalized() {
+        let srt = format!("\u{FEFF}{}", SRT.replace('\n', "\r\n"));
+        assert_eq!(to_vtt(&srt).as_deref(), Some(VTT));
+
+        let vtt = format!("\u{FEFF}{}", VTT.replace('\n', "\r\n"));
+        assert_eq!(to_vtt(&vtt).as_deref(), Some(VTT));
+    }
+
+    #[test]
+    fn cue_numbers_are_optional() {
+        let srt = "00:00:01,000 --> 00:00:02,500\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+        assert_eq!(to_vtt(srt).as_deref(), Some(VTT));
+    }
+
+    #[test]
+    fn broken_cues_are_skipped() {
+        let srt = format!("1\nnot a timing\nLost\n\n{SRT}");
+        assert_eq!(to_vtt(&srt).as_deref(), Some(VTT));
+        assert_eq!(to_vtt("just some text"), None);
+    }
+
+    #[test]
+    fn font_tags_and_arrows_are_stripped() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000 X1:0 X2:10\n<font color=\"red\"><i>a --> b</i></font>\n";
+        assert_eq!(to_vtt(srt).as_deref(), Some("WEBVTT\n\n00:00:01.000 --> 00:00:02.000\n<i>a -> b</i>\n"));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use dashmap::DashMap;
+
+use crate::clock;
+use crate::model::{StatsReport, StreamHealth};
+
+const WINDOW: Duration = Duration::from_secs(60);
+const STRUGGLING_WINDOW: Duration = Duration::from_secs(10);
+const PUSH_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_REPORTS_PER_VIEWER: usize = 120;
+
+// rolling window of viewer reports, one per room, viewers that stop reporting age out
+#[derive(Debug, Default)]
+pub struct Telemetry {
+    viewers:    DashMap<i32, VecDeque<(Instant, StatsReport)>>, // user_id -> reports, oldest first
+    last_push:  Mutex<Option<Instant>>,
+}
+
+impl Telemetry {
+    pub fn report(&self, user_id: i32, report: StatsReport) -> Result<(), &'static str> {
+        report.validate()?;
+        let mut reports = self.viewers.entry(user_id).or_default();
+        reports.push_back((Instant::now(), report));
+        while reports.len() > MAX_REPORTS_PER_VIEWER {
+            reports.pop_front();
+        }
+        Ok(())
+    }
+
+    // true at most once per push interval
+    pub fn should_push(&self) -> bool {
+        let mut last_push = self.last_push.lock().unwrap();
+        if last_push.is_some_and(|t| t.elapsed() < PUSH_INTERVAL) { return false }
+        *last_push = Some(Instant::now());
+        true
+    }
+
+    pub fn summary(&self) -> StreamHealth {
+        let now = Instant::now();
+        self.viewers.retain(|_, reports| {
+            while reports.front().is_some_and(|(t, _)| now - *t > WINDOW) {
+                reports.pop_front();
+            }
+            !reports.is_empty()
+        });
+
+        let mut bitrates = vec![];
+        let mut dropped = vec![];
+        let mut stalls = vec![];
+        let mut struggling = 0;
+        for reports in self.viewers.iter() {
+            let len = reports.len() as f64;
+            bitrates.push(reports.iter().map(|(_, r)| r.bitrate).sum::<f64>() / len);
+            dropped.push(reports.iter().map(|(_, r)| r.dropped_frames as f64).sum::<f64>());
+            stalls.push(reports.iter().fold(0u64, |sum, (_, r)| sum.saturating_add(r.stalls as u64)));
+            if reports.iter().any(|(t, r)| r.stalls > 0 && now - *t <= STRUGGLING_WINDOW) {
+                struggling += 1;
+            }
+        }
+
+        let mean = |values: &[f64]| if values.is_empty() { 0.0 } else { values.iter().sum::<f64>() / values.len() as f64 };
+        // nearest-rank percentile
+        stalls.sort_unstable();
+        let p95_stalls = match stalls.len() {
+            0 => 0,
+            len => stalls[((len as f64 * 0.95).ceil() as usize).clamp(1, len) - 1],
+        };
+
+        StreamHealth {
+            window_s: WINDOW.as_secs(),
+            viewers: stalls.len(),
+            struggling,
+            avg_bitrate: mean(&bitrates),
+            avg_dropped_frames: mean(&dropped),
+            p95_stalls,
+            updated_at: clock::now(),
+        }
+    }
+}
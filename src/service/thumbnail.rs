@@ -0,0 +1,39 @@
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+
+use super::room::RoomError;
+
+const MAX_THUMBNAIL_BYTES: usize = 512 * 1024;
+
+// latest stream snapshot of a room, kept in memory only
+#[derive(Debug)]
+pub struct Thumbnail {
+    pub mime:       &'static str,
+    pub data:       Bytes,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Thumbnail {
+    // the declared content type must match the image magic
+    pub fn new(content_type: &str, data: Bytes) -> Result<Self, RoomError> {
+        const JPEG_MAGIC: [u8; 3] = [0xFF, 0xD8, 0xFF];
+        const PNG_MAGIC: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+        if data.len() > MAX_THUMBNAIL_BYTES { return Err(RoomError::InvalidArgument("Thumbnail too large")) }
+        let mime = match content_type.split(';').next().unwrap_or_default().trim() {
+            "image/jpeg" if data.starts_with(&JPEG_MAGIC) => "image/jpeg",
+            "image/png" if data.starts_with(&PNG_MAGIC) => "image/png",
+            _ => return Err(RoomError::InvalidArgument("Thumbnail must be a JPEG or PNG image")),
+        };
+        Ok(Self { mime, data, updated_at: Utc::now() })
+    }
+
+    // changes with every upload, used as cache buster and etag
+    pub fn version(&self) -> i64 {
+        self.updated_at.timestamp_millis()
+    }
+
+    pub fn etag(&self) -> String {
+        format!("\"{:x}\"", self.version())
+    }
+}
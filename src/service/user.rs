@@ -49,11 +49,11 @@ fn validate_password(password: &str) -> bool {
     has_digit && has_letter
 }
 
-fn bcrypt_password(password: &str, cost: u32) -> String {
+pub fn bcrypt_password(password: &str, cost: u32) -> String {
     bcrypt::hash(password, cost).unwrap()
 }
 
-fn verify_password(password: &str, hash: &str) -> bool {
+pub fn verify_password(password: &str, hash: &str) -> bool {
     bcrypt::verify(password, hash).unwrap()
 }
 
@@ -178,4 +178,9 @@ pub async fn update_profile(repo: Arc<dyn Repository>, user_id: i32, param: Upda
 pub async fn get_user_by_id(repo: Arc<dyn Repository>, user_id: i32) -> Result<UserModel, UserError> {
     let user = repo.find_by_id(user_id).await.ok_or(UserError::UserNotFound)?;
     Ok(user)
-}
\ No newline at end of file
+}
+
+pub async fn get_user_by_name(repo: Arc<dyn Repository>, name: &str) -> Result<UserModel, UserError> {
+    let user = repo.find_by_name(name).await.map_err(super::Error::from)?;
+    user.ok_or(UserError::UserNotFound)
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{Ping, Pong};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IceCandidate {
+    pub candidate: String,
+    #[serde(rename = "sdpMid")]
+    pub sdp_mid: Option<String>,
+    #[serde(rename = "sdpMLineIndex")]
+    pub sdp_m_line_index: Option<u16>,
+}
+
+// client -> server, `track` names the publisher whose media is negotiated
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "content")]
+pub enum SignalRequest {
+    #[serde(rename = "offer")]
+    Offer {
+        to: Option<i32>,
+        track: Option<i32>,
+        sdp: String,
+    },
+    #[serde(rename = "answer")]
+    Answer {
+        to: Option<i32>,
+        track: Option<i32>,
+        sdp: String,
+    },
+    #[serde(rename = "ice_candidate")]
+    IceCandidate {
+        to: Option<i32>,
+        track: Option<i32>,
+        candidate: IceCandidate,
+    },
+}
+
+impl SignalRequest {
+    pub fn target(&self) -> Option<i32> {
+        match self {
+            SignalRequest::Offer { to, .. }         |
+            SignalRequest::Answer { to, .. }        |
+            SignalRequest::IceCandidate { to, .. }  => *to,
+        }
+    }
+
+    pub fn track(&self) -> Option<i32> {
+        match self {
+            SignalRequest::Offer { track, .. }          |
+            SignalRequest::Answer { track, .. }         |
+            SignalRequest::IceCandidate { track, .. }   => *track,
+        }
+    }
+
+    pub fn into_event(self, from: i32, track: i32) -> SignalEvent {
+        match self {
+            SignalRequest::Offer { sdp, .. } => SignalEvent::Offer { from, track, sdp },
+            SignalRequest::Answer { sdp, .. } => SignalEvent::Answer { from, track, sdp },
+            SignalRequest::IceCandidate { candidate, .. } => SignalEvent::IceCandidate { from, track, candidate },
+        }
+    }
+}
+
+// client -> server, answered by the server itself instead of being relayed
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "content")]
+pub enum SignalControl {
+    #[serde(rename = "ping")]
+    Ping(Ping),
+}
+
+// server -> client
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "content")]
+pub enum SignalEvent {
+    #[serde(rename = "welcome")]
+    Welcome {
+        user_id: i32,
+        host_id: i32,
+        publishers: Vec<i32>,
+        peers: Vec<i32>,
+    },
+    #[serde(rename = "peer_joined")]
+    PeerJoined {
+        user_id: i32,
+        publisher: bool,
+    },
+    #[serde(rename = "peer_left")]
+    PeerLeft {
+        user_id: i32,
+    },
+    #[serde(rename = "offer")]
+    Offer {
+        from: i32,
+        track: i32,
+        sdp: String,
+    },
+    #[serde(rename = "answer")]
+    Answer {
+        from: i32,
+        track: i32,
+        sdp: String,
+    },
+    #[serde(rename = "ice_candidate")]
+    IceCandidate {
+        from: i32,
+        track: i32,
+        candidate: IceCandidate,
+    },
+    #[serde(rename = "pong")]
+    Pong(Pong),
+    #[serde(rename = "closed")]
+    Closed {
+        reason: String,
+    },
+    #[serde(rename = "error")]
+    Error {
+        reason: String,
+    },
+}
+
+impl SignalEvent {
+    pub fn error(reason: &str) -> Self {
+        SignalEvent::Error { reason: reason.to_string() }
+    }
+}
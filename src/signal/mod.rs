@@ -1,48 +1,244 @@
-use axum::{
-    routing::get,
-    Router,
-    extract::ws::{WebSocket, WebSocketUpgrade},
-    response::Response,
-};
-use std::net::SocketAddr;
-use tokio::sync::broadcast;
+mod message;
+
+use std::sync::{Arc, LazyLock};
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
+use dashmap::DashMap;
 use futures::{SinkExt, StreamExt};
+use thiserror::Error as ThisError;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+use crate::clock;
+use crate::model::{CloseCode, StreamRole};
+use crate::service::room::{self, Room};
+use message::{SignalControl, SignalEvent, SignalRequest};
+
+const MPSC_BUF_SIZE: usize = 32;
+
+type PeerSender = mpsc::Sender<Arc<SignalEvent>>;
+type Kick = Arc<watch::Sender<Option<(CloseCode, String)>>>;
+type Peers = Arc<DashMap<i32, Peer>>; // user_id -> peer
+
+struct Peer {
+    tx: PeerSender,
+    kick: Kick, // out of band so a full queue cannot swallow it
+}
+
+// room link -> connected peers
+static SESSIONS: LazyLock<DashMap<String, Peers>> = LazyLock::new(DashMap::new);
 
-type BroadcastChannel = broadcast::Sender<String>;
+#[derive(ThisError, Debug)]
+pub enum SignalError {
+    #[error("{0}")]
+    RoomError(#[from] room::RoomError),
 
-async fn ws_handler(ws: WebSocketUpgrade) -> Response {
-    ws.on_upgrade(handle_socket)
+    #[error("Socket error")]
+    SocketError(#[from] axum::Error),
+
+    #[error("Stream role does not match the room")]
+    RoleMismatch,
+
+    #[error("Internal error")]
+    InternalError,
 }
 
-async fn handle_socket(mut socket: WebSocket) {
-    let (tx, _) = broadcast::channel(100);
-    
-    let (mut sender, mut receiver) = socket.split();
+fn register(room_link: &str, user_id: i32, tx: PeerSender, kick: Kick) -> Peers {
+    let peers = SESSIONS.entry(room_link.to_string()).or_default();
+    peers.insert(user_id, Peer { tx, kick });
+    peers.clone()
+}
+
+// returns false if the peer has already been replaced by a newer connection
+fn unregister(room_link: &str, user_id: i32, tx: &PeerSender) -> bool {
+    let mut removed = false;
+    SESSIONS.remove_if(room_link, |_, peers| {
+        removed = peers.remove_if(&user_id, |_, p| p.tx.same_channel(tx)).is_some();
+        peers.is_empty()
+    });
+    removed
+}
+
+// publishers talk to everyone, viewers only talk to publishers
+fn visible_to(room: &Room, viewer: i32, peer: i32) -> bool {
+    viewer != peer && (room.can_publish(viewer) || room.can_publish(peer))
+}
+
+async fn announce(peers: &Peers, room: &Room, user_id: i32, event: SignalEvent) {
+    let event = Arc::new(event);
+    let targets: Vec<PeerSender> = peers.iter()
+        .filter(|p| visible_to(room, user_id, *p.key()))
+        .map(|p| p.tx.clone())
+        .collect();
+
+    for tx in targets {
+        let _ = tx.send(event.clone()).await;
+    }
+}
+
+// end a kicked or banned user's signaling socket, unrouted at once so nothing reaches them meanwhile
+pub fn expel(room_link: &str, user_id: i32, code: CloseCode, reason: &str) {
+    let mut peer = None;
+    SESSIONS.remove_if(room_link, |_, peers| {
+        peer = peers.remove(&user_id).map(|(_, p)| p);
+        peers.is_empty()
+    });
+    if let Some(peer) = peer {
+        peer.kick.send_replace(Some((code, reason.to_string())));
+    }
+}
+
+async fn relay(peers: &Peers, to: i32, event: SignalEvent) -> bool {
+    let tx = peers.get(&to).map(|p| p.tx.clone());
+    match tx {
+        Some(tx) => tx.send(Arc::new(event)).await.is_ok(),
+        None => false,
+    }
+}
+
+// whether any audio/video section of the sdp sends media, direction defaults to sendrecv
+fn publishes_media(sdp: &str) -> bool {
+    let mut sending = false;
+    let mut in_media = false;
+    for line in sdp.lines().map(str::trim) {
+        if let Some(media) = line.strip_prefix("m=") {
+            if sending { return true }
+            in_media = media.starts_with("audio") || media.starts_with("video");
+            sending = in_media;
+        } else if in_media && matches!(line, "a=recvonly" | "a=inactive") {
+            sending = false;
+        }
+    }
+    sending
+}
+
+async fn route(peers: &Peers, room: &Room, user_id: i32, req: SignalRequest) -> Result<(), &'static str> {
+    let publisher = room.can_publish(user_id);
+    if !publisher
+        && let SignalRequest::Offer { sdp, .. } | SignalRequest::Answer { sdp, .. } = &req
+        && publishes_media(sdp) {
+        return Err("Viewers may not publish media");
+    }
+
+    let to = match (publisher, req.target()) {
+        (true, Some(to)) => to,
+        (true, None) => return Err("Missing target peer"),
+        (false, Some(to)) if !room.can_publish(to) => return Err("Viewers may only signal publishers"),
+        (false, Some(to)) => to,
+        (false, None) => room.host_id(),
+    };
+
+    // the negotiated track belongs to one of the two peers, by default the publishing one
+    let track = match req.track() {
+        Some(track) if (track == user_id || track == to) && room.can_publish(track) => track,
+        Some(_) => return Err("Unknown track"),
+        None if publisher => user_id,
+        None => to,
+    };
+
+    if !relay(peers, to, req.into_event(user_id, track)).await {
+        return Err("Peer not connected");
+    }
+    Ok(())
+}
+
+pub async fn handle_websocket(
+    socket: WebSocket, room_link: String,
+    user_id: i32, role: StreamRole
+) -> Result<(), SignalError> {
+    let room = room::get_room_by_link(&room_link)?;
+    let host_id = room.host_id();
+    if role.is_publisher() != room.stream_role(user_id)?.is_publisher() {
+        return Err(SignalError::RoleMismatch);
+    }
+
+    let (tx, mut rx) = mpsc::channel::<Arc<SignalEvent>>(MPSC_BUF_SIZE);
+    let (kick, kicked) = watch::channel(None);
+    let kick = Arc::new(kick);
+    let (mut sender, mut recver) = socket.split();
+
+    let peers = register(&room_link, user_id, tx.clone(), kick.clone());
+    let visible = peers.iter()
+        .map(|p| *p.key())
+        .filter(|&peer| visible_to(&room, user_id, peer))
+        .collect();
+    let welcome = SignalEvent::Welcome { user_id, host_id, publishers: room.publishers(), peers: visible };
+    tx.send(Arc::new(welcome)).await
+        .map_err(|_| SignalError::InternalError)?;
+    let publisher = role.is_publisher();
+    announce(&peers, &room, user_id, SignalEvent::PeerJoined { user_id, publisher }).await;
 
     let _tx = tx.clone();
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(msg)) = receiver.next().await {
-            if let Ok(text) = msg.into_text() {
-                let _ = _tx.send(text);
+    let _peers = peers.clone();
+    let _room = room.clone();
+    let _kicked = kicked.clone();
+    let recv_fut = async move {
+        while let Some(Ok(msg)) = recver.next().await {
+            if _kicked.borrow().is_some() { break }
+            let received_at = clock::now_ms();
+            _room.touch();
+            let Message::Text(text) = msg else { continue };
+            let res = if let Ok(req) = serde_json::from_str::<SignalRequest>(&text) {
+                route(&_peers, &_room, user_id, req).await
+            } else if let Ok(SignalControl::Ping(ping)) = serde_json::from_str::<SignalControl>(&text) {
+                _tx.send(Arc::new(SignalEvent::Pong(ping.pong(received_at)))).await
+                    .map_err(|_| SignalError::InternalError)?;
+                Ok(())
+            } else {
+                Err("Bad signal message")
+            };
+            if let Err(reason) = res {
+                _tx.send(Arc::new(SignalEvent::error(reason))).await
+                    .map_err(|_| SignalError::InternalError)?;
             }
         }
-    });
-    
-    let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = tx.subscribe().recv().await {
-            if sender.send(axum::extract::ws::Message::Text(msg)).await.is_err() {
-                break;
-            }
+        Ok(())
+    };
+
+    let closing_room = room.clone();
+    let mut _kicked = kicked.clone();
+    let send_fut = async move {
+        loop {
+            let event = tokio::select! {
+                biased;
+                Ok(()) = _kicked.changed() => {
+                    let Some((code, reason)) = _kicked.borrow_and_update().clone() else { continue };
+                    let frame = CloseFrame { code: code as u16, reason: reason.into() };
+                    sender.send(Message::Close(Some(frame))).await?;
+                    break;
+                }
+                event = rx.recv() => event,
+                reason = closing_room.closed() => {
+                    let text = serde_json::to_string(&SignalEvent::Closed { reason: reason.clone() })
+                        .map_err(|_| SignalError::InternalError)?;
+                    sender.send(Message::Text(text.into())).await?;
+                    let frame = CloseFrame { code: CloseCode::RoomClosed as u16, reason: reason.into() };
+                    sender.send(Message::Close(Some(frame))).await?;
+                    break;
+                }
+            };
+            let Some(event) = event else { break };
+            let text = serde_json::to_string(event.as_ref()).map_err(|_| SignalError::InternalError)?;
+            sender.send(Message::Text(text.into())).await?;
         }
-    });
-    
+        Ok(())
+    };
+
+    let mut recv_task: JoinHandle<Result<(), SignalError>> = tokio::spawn(recv_fut);
+    let mut send_task: JoinHandle<Result<(), SignalError>> = tokio::spawn(send_fut);
+
     tokio::select! {
-        _ = &mut recv_task => send_task.abort(),
-        _ = &mut send_task => recv_task.abort(),
+        _ = &mut recv_task => {
+            send_task.abort();
+        }
+        _ = &mut send_task => {
+            recv_task.abort();
+        }
     }
-}
 
-pub fn signal_router() -> Router {
-    Router::new()
-        .route("/ws", get(ws_handler))
-}
\ No newline at end of file
+    // an expelled peer is already unrouted, the others still have to see it leave
+    if unregister(&room_link, user_id, &tx) || kicked.borrow().is_some() {
+        announce(&peers, &room, user_id, SignalEvent::PeerLeft { user_id }).await;
+    }
+
+    Ok(())
+}